@@ -5,6 +5,41 @@ use std::fs::File;
 use std::io::Write;
 use std::path::Path;
 
+/// The field holding the keyword that introduces each kind of node that has
+/// one, for `Node::leading_keyword_loc`, along with the keyword the field has
+/// to hold if it can also hold other tokens (the `else` of an `ElseNode` is the
+/// `:` of a ternary).
+const LEADING_KEYWORDS: &[(&str, &str, Option<&str>)] = &[
+    ("AliasGlobalVariableNode", "keyword_loc", None),
+    ("AliasMethodNode", "keyword_loc", None),
+    ("BeginNode", "begin_keyword_loc", None),
+    ("BreakNode", "keyword_loc", None),
+    ("CaseMatchNode", "case_keyword_loc", None),
+    ("CaseNode", "case_keyword_loc", None),
+    ("ClassNode", "class_keyword_loc", None),
+    ("DefNode", "def_keyword_loc", None),
+    ("DefinedNode", "keyword_loc", None),
+    ("ElseNode", "else_keyword_loc", Some("else")),
+    ("EnsureNode", "ensure_keyword_loc", None),
+    ("ForNode", "for_keyword_loc", None),
+    ("IfNode", "if_keyword_loc", None),
+    ("InNode", "in_loc", None),
+    ("ModuleNode", "module_keyword_loc", None),
+    ("NextNode", "keyword_loc", None),
+    ("PostExecutionNode", "keyword_loc", None),
+    ("PreExecutionNode", "keyword_loc", None),
+    ("RescueNode", "keyword_loc", None),
+    ("ReturnNode", "keyword_loc", None),
+    ("SingletonClassNode", "class_keyword_loc", None),
+    ("SuperNode", "keyword_loc", None),
+    ("UndefNode", "keyword_loc", None),
+    ("UnlessNode", "keyword_loc", None),
+    ("UntilNode", "keyword_loc", None),
+    ("WhenNode", "keyword_loc", None),
+    ("WhileNode", "keyword_loc", None),
+    ("YieldNode", "keyword_loc", None),
+];
+
 #[derive(Debug, Deserialize)]
 enum NodeFieldType {
     #[serde(rename = "node")]
@@ -338,11 +373,27 @@ fn write_node(file: &mut File, flags: &[Flags], node: &Node) -> Result<(), Box<d
                         writeln!(file, "        (self.{}() & {}) != 0", field.name, enum_const_name(&flag.name, &value.name))?;
                         writeln!(file, "    }}")?;
                     }
+
+                    write_flag_names(file, flag)?;
                 }
             },
         }
     }
 
+    // The structure and events of nodes without fields don't depend on the
+    // node, so `Node` writes them itself.
+    if has_structure_fields(node) {
+        writeln!(file)?;
+        write_structure(file, node)?;
+    }
+    writeln!(file)?;
+    write_json(file, node)?;
+    writeln!(file)?;
+    write_fingerprint(file, node)?;
+    if !node.fields.is_empty() {
+        writeln!(file)?;
+        write_events(file, node)?;
+    }
     writeln!(file, "}}")?;
     writeln!(file)?;
 
@@ -381,6 +432,272 @@ fn write_node(file: &mut File, flags: &[Flags], node: &Node) -> Result<(), Box<d
     Ok(())
 }
 
+/// Returns the fields of the node paired with the expressions that read them
+/// in the generated code, for the methods that walk every field of a node.
+/// Node fields are read as `Node`s even when config.yml names a more specific
+/// kind, string fields as their unescaped bytes, and flags fields as the
+/// lowercase names of the flags that are set.
+fn field_values(node: &Node) -> Vec<(&NodeField, String)> {
+    node.fields
+        .iter()
+        .map(|field| {
+            let value = match (&field.field_type, &field.kind) {
+                (NodeFieldType::Node, Some(_)) => format!("self.{}().as_node()", field.name),
+                (NodeFieldType::OptionalNode, Some(kind)) => format!("self.{}().as_ref().map({}::as_node)", field.name, kind),
+                (NodeFieldType::String, _) => format!("crate::string_bytes(unsafe {{ &(*self.pointer).{} }})", field.name),
+                (NodeFieldType::Flags, _) => "self.flag_names()".to_string(),
+                _ => format!("self.{}()", field.name),
+            };
+
+            (field, value)
+        })
+        .collect()
+}
+
+/// Write the methods that return the flags of the node by name, which
+/// `field_values` and `Node::flag_is_set` read flags with.
+fn write_flag_names(file: &mut File, flag: &Flags) -> Result<(), Box<dyn std::error::Error>> {
+    let values = flag.values.iter().map(|value| format!("(\"{}\", self.{}())", value.name.to_lowercase(), accessor_func_name(&value.name))).collect::<Vec<_>>();
+
+    writeln!(file)?;
+    writeln!(file, "    /// Returns the lowercase name of each flag with whether it is set.")?;
+    writeln!(file, "    fn flag_values(&self) -> [(&'static str, bool); {}] {{", values.len())?;
+    writeln!(file, "        [{}]", values.join(", "))?;
+    writeln!(file, "    }}")?;
+    writeln!(file)?;
+    writeln!(file, "    /// Returns the lowercase names of the flags that are set.")?;
+    writeln!(file, "    fn flag_names(&self) -> Vec<&'static str> {{")?;
+    writeln!(file, "        self.flag_values().into_iter().filter_map(|(name, set)| set.then_some(name)).collect()")?;
+    writeln!(file, "    }}")?;
+    writeln!(file)?;
+    writeln!(file, "    /// Returns whether the flag with the given name is set, compared")?;
+    writeln!(file, "    /// case-insensitively, or `None` if there is no such flag.")?;
+    writeln!(file, "    fn flag_value(&self, name: &str) -> Option<bool> {{")?;
+    writeln!(file, "        self.flag_values().into_iter().find(|(flag, _)| name.eq_ignore_ascii_case(flag)).map(|(_, set)| set)")?;
+    writeln!(file, "    }}")?;
+
+    Ok(())
+}
+
+/// Returns whether the node has fields that are part of its structure, which
+/// leaves out locations.
+fn has_structure_fields(node: &Node) -> bool {
+    node.fields.iter().any(|field| !matches!(field.field_type, NodeFieldType::Location | NodeFieldType::OptionalLocation))
+}
+
+/// Write the method that writes the location-free structure of the node (see
+/// `Node::structure_debug`) to the file.
+fn write_structure(file: &mut File, node: &Node) -> Result<(), Box<dyn std::error::Error>> {
+    writeln!(file, "    /// Writes the structure of this node to the given string, indented by")?;
+    writeln!(file, "    /// the given number of spaces.")?;
+    writeln!(file, "    fn write_structure(&self, out: &mut String, indent: usize) {{")?;
+    writeln!(file, "        structure_line(out, indent, \"{}\");", node.name)?;
+
+    for (field, value) in field_values(node) {
+        match field.field_type {
+            NodeFieldType::Node => {
+                writeln!(file, "        structure_line(out, indent + 2, \"{}:\");", field.name)?;
+                writeln!(file, "        {}.write_structure(out, indent + 4);", value)?;
+            },
+            NodeFieldType::OptionalNode => {
+                writeln!(file, "        if let Some(node) = {} {{", value)?;
+                writeln!(file, "            structure_line(out, indent + 2, \"{}:\");", field.name)?;
+                writeln!(file, "            node.write_structure(out, indent + 4);")?;
+                writeln!(file, "        }} else {{")?;
+                writeln!(file, "            structure_line(out, indent + 2, \"{}: nil\");", field.name)?;
+                writeln!(file, "        }}")?;
+            },
+            NodeFieldType::NodeList => {
+                writeln!(file, "        let nodes = {}.iter().collect::<Vec<_>>();", value)?;
+                writeln!(file, "        if nodes.is_empty() {{")?;
+                writeln!(file, "            structure_line(out, indent + 2, \"{}: []\");", field.name)?;
+                writeln!(file, "        }} else {{")?;
+                writeln!(file, "            structure_line(out, indent + 2, \"{}:\");", field.name)?;
+                writeln!(file, "            for node in nodes {{")?;
+                writeln!(file, "                node.write_structure(out, indent + 4);")?;
+                writeln!(file, "            }}")?;
+                writeln!(file, "        }}")?;
+            },
+            NodeFieldType::Constant => {
+                writeln!(file, "        structure_line(out, indent + 2, &format!(\"{}: {{}}\", constant_debug(&{})));", field.name, value)?;
+            },
+            NodeFieldType::OptionalConstant => {
+                writeln!(file, "        let constant = {}.map_or_else(|| \"nil\".to_string(), |constant| constant_debug(&constant));", value)?;
+                writeln!(file, "        structure_line(out, indent + 2, &format!(\"{}: {{constant}}\"));", field.name)?;
+            },
+            NodeFieldType::ConstantList => {
+                writeln!(file, "        let constants = {}.iter().map(|constant| constant_debug(&constant)).collect::<Vec<_>>();", value)?;
+                writeln!(file, "        structure_line(out, indent + 2, &format!(\"{}: [{{}}]\", constants.join(\", \")));", field.name)?;
+            },
+            NodeFieldType::UInt8 | NodeFieldType::UInt32 => {
+                writeln!(file, "        structure_line(out, indent + 2, &format!(\"{}: {{}}\", {}));", field.name, value)?;
+            },
+            NodeFieldType::Flags => {
+                writeln!(file, "        structure_line(out, indent + 2, &format!(\"{}: [{{}}]\", {}.join(\", \")));", field.name, value)?;
+            },
+            NodeFieldType::String => {
+                writeln!(file, "        structure_line(out, indent + 2, &format!(\"{}: {{:?}}\", String::from_utf8_lossy({})));", field.name, value)?;
+            },
+            NodeFieldType::Location | NodeFieldType::OptionalLocation => {},
+        }
+    }
+
+    writeln!(file, "    }}")?;
+
+    Ok(())
+}
+
+/// Write the method that builds the JSON value of the node (see
+/// `Node::to_json_value`) to the file.
+fn write_json(file: &mut File, node: &Node) -> Result<(), Box<dyn std::error::Error>> {
+    writeln!(file, "    /// Returns this node and its descendants as a JSON value.")?;
+    writeln!(file, "    #[cfg(feature = \"json\")]")?;
+    writeln!(file, "    fn to_json_value(&self) -> serde_json::Value {{")?;
+    writeln!(file, "        let mut object = serde_json::Map::new();")?;
+    writeln!(file, "        object.insert(\"type\".to_string(), \"{}\".into());", node.name)?;
+    writeln!(file, "        object.insert(\"location\".to_string(), location_json(&self.location()));")?;
+
+    for (field, value) in field_values(node) {
+        let json = match field.field_type {
+            NodeFieldType::Node => format!("{}.to_json_value()", value),
+            NodeFieldType::OptionalNode => format!("{}.map_or(serde_json::Value::Null, |node| node.to_json_value())", value),
+            NodeFieldType::NodeList => format!("{}.iter().map(|node| node.to_json_value()).collect()", value),
+            NodeFieldType::Constant => format!("constant_json(&{})", value),
+            NodeFieldType::OptionalConstant => format!("{}.map_or(serde_json::Value::Null, |constant| constant_json(&constant))", value),
+            NodeFieldType::ConstantList => format!("{}.iter().map(|constant| constant_json(&constant)).collect()", value),
+            NodeFieldType::Location => format!("location_json(&{})", value),
+            NodeFieldType::OptionalLocation => format!("{}.map_or(serde_json::Value::Null, |location| location_json(&location))", value),
+            NodeFieldType::UInt8 | NodeFieldType::UInt32 | NodeFieldType::Flags => format!("{}.into()", value),
+            NodeFieldType::String => format!("String::from_utf8_lossy({}).into_owned().into()", value),
+        };
+
+        writeln!(file, "        object.insert(\"{}\".to_string(), {});", field.name, json)?;
+    }
+
+    writeln!(file, "        object.into()")?;
+    writeln!(file, "    }}")?;
+
+    Ok(())
+}
+
+/// Write the method that writes the fingerprint of the node (see
+/// `ParseResult::fingerprint`) to the file.
+fn write_fingerprint(file: &mut File, node: &Node) -> Result<(), Box<dyn std::error::Error>> {
+    writeln!(file, "    /// Writes the fingerprint line of this node, followed by the lines of")?;
+    writeln!(file, "    /// its descendants one level deeper.")?;
+    writeln!(file, "    fn write_fingerprint(&self, out: &mut String, depth: usize, label: &str) {{")?;
+    writeln!(file, "        out.extend(std::iter::repeat(' ').take(depth * 2));")?;
+    writeln!(file, "        out.push_str(label);")?;
+    writeln!(file, "        out.push_str(\"{}\");", node.name)?;
+    writeln!(file, "        fingerprint_location(out, \"\", Some(&self.location()));")?;
+
+    let fields = field_values(node);
+
+    for (field, value) in &fields {
+        match field.field_type {
+            NodeFieldType::Node | NodeFieldType::OptionalNode | NodeFieldType::NodeList => {},
+            NodeFieldType::Constant => {
+                writeln!(file, "        fingerprint_bytes(out, \"{}\", Some({}.as_slice()));", field.name, value)?;
+            },
+            NodeFieldType::OptionalConstant => {
+                writeln!(file, "        fingerprint_bytes(out, \"{}\", {}.map(|constant| constant.as_slice()));", field.name, value)?;
+            },
+            NodeFieldType::ConstantList => {
+                writeln!(file, "        out.push_str(\" {}=[\");", field.name)?;
+                writeln!(file, "        for (index, constant) in {}.iter().enumerate() {{", value)?;
+                writeln!(file, "            if index > 0 {{")?;
+                writeln!(file, "                out.push(',');")?;
+                writeln!(file, "            }}")?;
+                writeln!(file, "            fingerprint_quoted(out, constant.as_slice());")?;
+                writeln!(file, "        }}")?;
+                writeln!(file, "        out.push(']');")?;
+            },
+            NodeFieldType::Location => {
+                writeln!(file, "        fingerprint_location(out, \"{}\", Some(&{}));", field.name, value)?;
+            },
+            NodeFieldType::OptionalLocation => {
+                writeln!(file, "        fingerprint_location(out, \"{}\", {}.as_ref());", field.name, value)?;
+            },
+            NodeFieldType::UInt8 | NodeFieldType::UInt32 => {
+                writeln!(file, "        out.push_str(\" {}=\");", field.name)?;
+                writeln!(file, "        out.push_str(&{}.to_string());", value)?;
+            },
+            NodeFieldType::Flags => {
+                writeln!(file, "        out.push_str(\" {}=[\");", field.name)?;
+                writeln!(file, "        out.push_str(&{}.join(\",\"));", value)?;
+                writeln!(file, "        out.push(']');")?;
+            },
+            NodeFieldType::String => {
+                writeln!(file, "        fingerprint_bytes(out, \"{}\", Some({}));", field.name, value)?;
+            },
+        }
+    }
+
+    writeln!(file, "        out.push('\\n');")?;
+
+    for (field, value) in &fields {
+        match field.field_type {
+            NodeFieldType::Node => {
+                writeln!(file, "        {}.write_fingerprint(out, depth + 1, \"{}: \");", value, field.name)?;
+            },
+            NodeFieldType::OptionalNode => {
+                writeln!(file, "        if let Some(node) = {} {{", value)?;
+                writeln!(file, "            node.write_fingerprint(out, depth + 1, \"{}: \");", field.name)?;
+                writeln!(file, "        }}")?;
+            },
+            NodeFieldType::NodeList => {
+                writeln!(file, "        for (index, node) in {}.iter().enumerate() {{", value)?;
+                writeln!(file, "            node.write_fingerprint(out, depth + 1, &format!(\"{}[{{index}}]: \"));", field.name)?;
+                writeln!(file, "        }}")?;
+            },
+            _ => {},
+        }
+    }
+
+    writeln!(file, "    }}")?;
+
+    Ok(())
+}
+
+/// Write the method that pushes the events of the node (see `Node::events`)
+/// onto the work stack to the file. The events are pushed in reverse, since the
+/// stack is popped from the end.
+fn write_events(file: &mut File, node: &Node) -> Result<(), Box<dyn std::error::Error>> {
+    writeln!(file, "    /// Pushes the events of this node onto the given work stack.")?;
+    writeln!(file, "    fn push_events(&self, stack: &mut Vec<Work<'pr>>) {{")?;
+    writeln!(file, "        stack.push(Work::Event(Event::Leave(\"{}\")));", node.name)?;
+
+    for (field, value) in field_values(node).into_iter().rev() {
+        let work = match field.field_type {
+            NodeFieldType::Node => format!("Work::Node({})", value),
+            NodeFieldType::OptionalNode => format!("{}.map_or(Work::Event(Event::Scalar(Scalar::Nil)), Work::Node)", value),
+            NodeFieldType::NodeList => {
+                writeln!(file, "        let nodes = {}.iter().collect::<Vec<_>>();", value)?;
+                writeln!(file, "        let count = nodes.len();")?;
+                writeln!(file, "        stack.extend(nodes.into_iter().rev().map(Work::Node));")?;
+                "Work::Event(Event::Scalar(Scalar::List(count)))".to_string()
+            },
+            NodeFieldType::Constant => format!("Work::Event(Event::Scalar(Scalar::Constant({})))", value),
+            NodeFieldType::OptionalConstant => format!("Work::Event(Event::Scalar({}.map_or(Scalar::Nil, Scalar::Constant)))", value),
+            NodeFieldType::ConstantList => format!("Work::Event(Event::Scalar(Scalar::ConstantList({})))", value),
+            NodeFieldType::Location => format!("Work::Event(Event::Scalar(Scalar::Location({})))", value),
+            NodeFieldType::OptionalLocation => format!("Work::Event(Event::Scalar({}.map_or(Scalar::Nil, Scalar::Location)))", value),
+            NodeFieldType::UInt8 => format!("Work::Event(Event::Scalar(Scalar::Integer(u32::from({}))))", value),
+            NodeFieldType::UInt32 => format!("Work::Event(Event::Scalar(Scalar::Integer({})))", value),
+            NodeFieldType::Flags => format!("Work::Event(Event::Scalar(Scalar::Flags({})))", value),
+            NodeFieldType::String => format!("Work::Event(Event::Scalar(Scalar::Bytes({})))", value),
+        };
+
+        writeln!(file, "        stack.push({});", work)?;
+        writeln!(file, "        stack.push(Work::Event(Event::Field(\"{}\")));", field.name)?;
+    }
+
+    writeln!(file, "        stack.push(Work::Event(Event::Enter(\"{}\")));", node.name)?;
+    writeln!(file, "    }}")?;
+
+    Ok(())
+}
+
 /// Write the visit trait to the file.
 fn write_visit(file: &mut File, config: &Config) -> Result<(), Box<dyn std::error::Error>> {
     writeln!(file, "/// A trait for visiting the AST.")?;
@@ -481,6 +798,85 @@ fn write_visit(file: &mut File, config: &Config) -> Result<(), Box<dyn std::erro
     Ok(())
 }
 
+/// Returns true if the given node belongs to the operator-write group, which is
+/// every node that writes a binary operator result back to a name or a target
+/// (e.g. `@foo += 1`). Index operator writes have neither, so they are left out.
+fn is_operator_write(node: &Node) -> bool {
+    node.name.ends_with("OperatorWriteNode")
+        && node.fields.iter().any(|f| f.name == "operator" && matches!(f.field_type, NodeFieldType::Constant))
+        && node.fields.iter().any(|f| f.name == "value" && matches!(f.field_type, NodeFieldType::Node))
+        && node.fields.iter().any(|f| matches!(f.name.as_str(), "name" | "write_name" | "target"))
+}
+
+/// Write the operator write trait and its implementations to the file.
+fn write_operator_write(file: &mut File, config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+    let nodes = config.nodes.iter().filter(|n| is_operator_write(n)).collect::<Vec<_>>();
+
+    writeln!(file, "/// What an operator write node is writing to.")?;
+    writeln!(file, "#[derive(Debug)]")?;
+    writeln!(file, "pub enum OperatorWriteTarget<'pr> {{")?;
+    writeln!(file, "    /// The name of the variable, constant, or method being written.")?;
+    writeln!(file, "    Name(ConstantId<'pr>),")?;
+    writeln!(file)?;
+    writeln!(file, "    /// The node being written through, e.g. the constant path in `Foo::Bar += 1`.")?;
+    writeln!(file, "    Target(Node<'pr>),")?;
+    writeln!(file, "}}")?;
+    writeln!(file)?;
+    writeln!(file, "/// A trait implemented by every operator write node (`x += 1`, `@x -= 1`,")?;
+    writeln!(file, "/// `Foo::Bar *= 1`, `foo.bar /= 1`, ...), so that they can be handled")?;
+    writeln!(file, "/// uniformly without matching on each variant.")?;
+    writeln!(file, "pub trait OperatorWrite<'pr> {{")?;
+    writeln!(file, "    /// Returns what is being written to.")?;
+    writeln!(file, "    fn name_or_target(&self) -> OperatorWriteTarget<'pr>;")?;
+    writeln!(file)?;
+    writeln!(file, "    /// Returns the binary operator being applied, e.g. `+` for `x += 1`.")?;
+    writeln!(file, "    fn operator(&self) -> &str;")?;
+    writeln!(file)?;
+    writeln!(file, "    /// Returns the right-hand side of the operator write.")?;
+    writeln!(file, "    fn value(&self) -> Node<'pr>;")?;
+    writeln!(file, "}}")?;
+
+    for node in &nodes {
+        writeln!(file)?;
+        writeln!(file, "impl<'pr> OperatorWrite<'pr> for {}<'pr> {{", node.name)?;
+        writeln!(file, "    fn name_or_target(&self) -> OperatorWriteTarget<'pr> {{")?;
+        if node.fields.iter().any(|f| f.name == "target") {
+            writeln!(file, "        OperatorWriteTarget::Target(self.target().as_node())")?;
+        } else if node.fields.iter().any(|f| f.name == "name") {
+            writeln!(file, "        OperatorWriteTarget::Name(self.name())")?;
+        } else {
+            writeln!(file, "        OperatorWriteTarget::Name(self.write_name())")?;
+        }
+        writeln!(file, "    }}")?;
+        writeln!(file)?;
+        writeln!(file, "    fn operator(&self) -> &str {{")?;
+        writeln!(file, "        std::str::from_utf8({}::operator(self).as_slice()).expect(\"operators should be valid UTF-8\")", node.name)?;
+        writeln!(file, "    }}")?;
+        writeln!(file)?;
+        writeln!(file, "    fn value(&self) -> Node<'pr> {{")?;
+        writeln!(file, "        {}::value(self)", node.name)?;
+        writeln!(file, "    }}")?;
+        writeln!(file, "}}")?;
+    }
+
+    writeln!(file)?;
+    writeln!(file, "impl<'pr> Node<'pr> {{")?;
+    writeln!(file, "    /// Returns the node as an `OperatorWrite` trait object, if it is an operator")?;
+    writeln!(file, "    /// write node.")?;
+    writeln!(file, "    #[must_use]")?;
+    writeln!(file, "    pub fn as_operator_write(&self) -> Option<Box<dyn OperatorWrite<'pr> + 'pr>> {{")?;
+    writeln!(file, "        match *self {{")?;
+    for node in &nodes {
+        writeln!(file, "            Self::{} {{ parser, pointer, marker }} => Some(Box::new({} {{ parser, pointer, marker }})),", node.name, node.name)?;
+    }
+    writeln!(file, "            _ => None")?;
+    writeln!(file, "        }}")?;
+    writeln!(file, "    }}")?;
+    writeln!(file, "}}")?;
+
+    Ok(())
+}
+
 /// Write the bindings to the `$OUT_DIR/bindings.rs` file. We'll pull these into
 /// the actual library in `src/lib.rs`.
 fn write_bindings(config: &Config) -> Result<(), Box<dyn std::error::Error>> {
@@ -497,9 +893,12 @@ use std::ptr::NonNull;
 #[allow(clippy::wildcard_imports)]
 use ruby_prism_sys::*;
 
+use crate::events::{{Event, Scalar, Work}};
+
 /// A range in the source file.
+#[derive(Clone, Copy)]
 pub struct Location<'pr> {{
-    parser: NonNull<pm_parser_t>,
+    pub(crate) parser: NonNull<pm_parser_t>,
     pub(crate) start: *const u8,
     pub(crate) end: *const u8,
     marker: PhantomData<&'pr [u8]>
@@ -618,6 +1017,7 @@ impl std::fmt::Debug for NodeList<'_> {{
 }}
 
 /// A handle for a constant ID.
+#[derive(Clone, Copy)]
 pub struct ConstantId<'pr> {{
     parser: NonNull<pm_parser_t>,
     id: pm_constant_id_t,
@@ -629,6 +1029,12 @@ impl<'pr> ConstantId<'pr> {{
         ConstantId {{ parser, id, marker: PhantomData }}
     }}
 
+    /// Returns every constant in the parser's constant pool, keyed by its
+    /// bytes.
+    pub(crate) fn by_name(parser: NonNull<pm_parser_t>) -> std::collections::HashMap<&'pr [u8], Self> {{
+        crate::constants(parser).map(|(id, bytes)| (bytes, Self::new(parser, id))).collect()
+    }}
+
     /// Returns a byte slice for the constant ID.
     ///
     /// # Panics
@@ -642,6 +1048,13 @@ impl<'pr> ConstantId<'pr> {{
             std::slice::from_raw_parts(constant.start, constant.length)
         }}
     }}
+
+    /// Returns the raw id of the constant in the parser's constant pool. Ids
+    /// are only meaningful within the parse result they came from.
+    #[must_use]
+    pub const fn id(&self) -> u32 {{
+        self.id
+    }}
 }}
 
 impl std::fmt::Debug for ConstantId<'_> {{
@@ -702,6 +1115,87 @@ impl std::fmt::Debug for ConstantList<'_> {{
         write!(f, "{{:?}}", self.iter().collect::<Vec<_>>())
     }}
 }}
+
+/// Writes a single indented line of a node's structure.
+fn structure_line(out: &mut String, indent: usize, line: &str) {{
+    out.extend(std::iter::repeat(' ').take(indent));
+    out.push_str(line);
+    out.push('\n');
+}}
+
+/// Returns a location as a JSON object of its byte offsets.
+#[cfg(feature = "json")]
+fn location_json(location: &Location<'_>) -> serde_json::Value {{
+    let mut object = serde_json::Map::new();
+    object.insert("start_offset".to_string(), location.start_offset().into());
+    object.insert("end_offset".to_string(), location.end_offset().into());
+    object.into()
+}}
+
+/// Returns the name of a constant as a JSON string.
+#[cfg(feature = "json")]
+fn constant_json(constant: &ConstantId<'_>) -> serde_json::Value {{
+    String::from_utf8_lossy(constant.as_slice()).into_owned().into()
+}}
+
+/// Returns the name of a constant as a quoted string for a node's structure.
+fn constant_debug(constant: &ConstantId<'_>) -> String {{
+    format!("{{:?}}", String::from_utf8_lossy(constant.as_slice()))
+}}
+
+/// Writes a ` name="..."` field of a node's fingerprint, or ` name=nil`.
+fn fingerprint_bytes(out: &mut String, name: &str, bytes: Option<&[u8]>) {{
+    out.push(' ');
+    out.push_str(name);
+    out.push('=');
+
+    match bytes {{
+        Some(bytes) => fingerprint_quoted(out, bytes),
+        None => out.push_str("nil"),
+    }}
+}}
+
+/// Writes bytes as a quoted string for a node's fingerprint. Quotes and
+/// backslashes are escaped with a backslash, and bytes outside of printable
+/// ASCII are written as `\xNN`.
+fn fingerprint_quoted(out: &mut String, bytes: &[u8]) {{
+    out.push('"');
+    for &byte in bytes {{
+        match byte {{
+            b'"' | b'\\' => {{
+                out.push('\\');
+                out.push(char::from(byte));
+            }},
+            b' '..=b'~' => out.push(char::from(byte)),
+            _ => {{
+                const HEX: &[u8; 16] = b"0123456789ABCDEF";
+                out.push_str("\\x");
+                out.push(char::from(HEX[usize::from(byte >> 4)]));
+                out.push(char::from(HEX[usize::from(byte & 0xF)]));
+            }},
+        }}
+    }}
+    out.push('"');
+}}
+
+/// Writes a ` name=start..end` field of a node's fingerprint, or ` name=nil`.
+/// An empty name writes just ` start..end`, for the node's own location.
+fn fingerprint_location(out: &mut String, name: &str, location: Option<&Location<'_>>) {{
+    out.push(' ');
+    if !name.is_empty() {{
+        out.push_str(name);
+        out.push('=');
+    }}
+
+    match location {{
+        Some(location) => {{
+            out.push_str(&location.start_offset().to_string());
+            out.push_str("..");
+            out.push_str(&location.end_offset().to_string());
+        }},
+        None => out.push_str("nil"),
+    }}
+}}
 "#
     )?;
 
@@ -718,6 +1212,10 @@ impl std::fmt::Debug for ConstantList<'_> {{
     writeln!(file)?;
 
     writeln!(file, "/// An enum representing the different kinds of nodes that can be parsed.")?;
+    writeln!(file, "///")?;
+    writeln!(file, "/// A node is a read-only handle to a node that prism allocated, so copying")?;
+    writeln!(file, "/// it is free and the copies refer to the same node.")?;
+    writeln!(file, "#[derive(Clone, Copy)]")?;
     writeln!(file, "pub enum Node<'pr> {{")?;
 
     for node in &config.nodes {
@@ -737,6 +1235,17 @@ impl std::fmt::Debug for ConstantList<'_> {{
     writeln!(file, "}}")?;
     writeln!(file)?;
 
+    writeln!(file, "/// The kind of a [`Node`], without the node itself, as returned by")?;
+    writeln!(file, "/// [`Node::node_type`].")?;
+    writeln!(file, "#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]")?;
+    writeln!(file, "pub enum NodeType {{")?;
+    for node in &config.nodes {
+        writeln!(file, "    /// The kind of a {} node", node.name)?;
+        writeln!(file, "    {},", node.name)?;
+    }
+    writeln!(file, "}}")?;
+    writeln!(file)?;
+
     writeln!(
         file,
         r#"
@@ -773,10 +1282,217 @@ impl<'pr> Node<'pr> {{
     writeln!(file, "    }}")?;
     writeln!(file)?;
 
+    writeln!(file, "    /// Returns the snake case name of the kind of this node, e.g. `\"if_node\"`")?;
+    writeln!(file, "    /// for an `IfNode`.")?;
+    writeln!(file, "    #[must_use]")?;
+    writeln!(file, "    pub const fn kind_name(&self) -> &'static str {{")?;
+    writeln!(file, "        match *self {{")?;
+    for node in &config.nodes {
+        writeln!(file, "            Self::{} {{ .. }} => \"{}\",", node.name, &struct_name(&node.name)[1..])?;
+    }
+    writeln!(file, "        }}")?;
+    writeln!(file, "    }}")?;
+    writeln!(file)?;
+
+    writeln!(file, "    /// Returns the kind of this node.")?;
+    writeln!(file, "    #[must_use]")?;
+    writeln!(file, "    pub const fn node_type(&self) -> NodeType {{")?;
+    writeln!(file, "        match *self {{")?;
+    for node in &config.nodes {
+        writeln!(file, "            Self::{} {{ .. }} => NodeType::{},", node.name, node.name)?;
+    }
+    writeln!(file, "        }}")?;
+    writeln!(file, "    }}")?;
+    writeln!(file)?;
+
+    writeln!(file, "    /// Returns the structure of this node and its descendants as an indented")?;
+    writeln!(file, "    /// tree of node kinds and their non-location fields. Unlike the `Debug`")?;
+    writeln!(file, "    /// output, this doesn't change when the source code moves around, which")?;
+    writeln!(file, "    /// makes it a good fit for snapshot tests:")?;
+    writeln!(file, "    ///")?;
+    writeln!(file, "    /// ```")?;
+    writeln!(file, "    /// let result = ruby_prism::parse(b\"foo\");")?;
+    writeln!(file, "    /// let structure = result.node().structure_debug();")?;
+    writeln!(file, "    ///")?;
+    writeln!(file, "    /// // e.g. with insta: `insta::assert_snapshot!(structure);`")?;
+    writeln!(file, "    /// assert!(structure.starts_with(\"ProgramNode\\n  locals: []\\n  statements:\\n    StatementsNode\\n\"));")?;
+    writeln!(file, "    /// ```")?;
+    writeln!(file, "    #[must_use]")?;
+    writeln!(file, "    pub fn structure_debug(&self) -> String {{")?;
+    writeln!(file, "        let mut out = String::new();")?;
+    writeln!(file, "        self.write_structure(&mut out, 0);")?;
+    writeln!(file, "        out")?;
+    writeln!(file, "    }}")?;
+    writeln!(file)?;
+
+    writeln!(file, "    /// Returns this node and its descendants as an untyped JSON tree, for")?;
+    writeln!(file, "    /// querying with tools like `JSONPath`. Every node is an object with a")?;
+    writeln!(file, "    /// `type` key holding the node's name (e.g. `\"CallNode\"`), a `location`")?;
+    writeln!(file, "    /// key, and a key for each of its fields:")?;
+    writeln!(file, "    ///")?;
+    writeln!(file, "    /// * child nodes are nested objects, or `null` if an optional child is missing")?;
+    writeln!(file, "    /// * node lists are arrays of objects")?;
+    writeln!(file, "    /// * constants are strings, and constant lists are arrays of strings")?;
+    writeln!(file, "    /// * locations are objects with `start_offset` and `end_offset` byte offsets")?;
+    writeln!(file, "    /// * integers are numbers, and flags are arrays of the names of the set flags")?;
+    writeln!(file, "    /// * strings are their unescaped contents, with invalid UTF-8 replaced by U+FFFD")?;
+    writeln!(file, "    #[cfg(feature = \"json\")]")?;
+    writeln!(file, "    #[must_use]")?;
+    writeln!(file, "    pub fn to_json_value(&self) -> serde_json::Value {{")?;
+    writeln!(file, "        match *self {{")?;
+    for node in &config.nodes {
+        writeln!(file, "            Self::{} {{ parser, pointer, marker }} => {} {{ parser, pointer, marker }}.to_json_value(),", node.name, node.name)?;
+    }
+    writeln!(file, "        }}")?;
+    writeln!(file, "    }}")?;
+    writeln!(file)?;
+
+    writeln!(file, "    /// Writes the structure of this node to the given string.")?;
+    writeln!(file, "    fn write_structure(&self, out: &mut String, indent: usize) {{")?;
+    writeln!(file, "        match *self {{")?;
+    for node in &config.nodes {
+        if has_structure_fields(node) {
+            writeln!(file, "            Self::{} {{ parser, pointer, marker }} => {} {{ parser, pointer, marker }}.write_structure(out, indent),", node.name, node.name)?;
+        } else {
+            writeln!(file, "            Self::{} {{ .. }} => structure_line(out, indent, \"{}\"),", node.name, node.name)?;
+        }
+    }
+    writeln!(file, "        }}")?;
+    writeln!(file, "    }}")?;
+    writeln!(file)?;
+
+    writeln!(file, "    /// Pushes the events of this node onto the given work stack (see")?;
+    writeln!(file, "    /// `Node::events`).")?;
+    writeln!(file, "    pub(crate) fn push_events(&self, stack: &mut Vec<Work<'pr>>) {{")?;
+    writeln!(file, "        match *self {{")?;
+    for node in &config.nodes {
+        if node.fields.is_empty() {
+            writeln!(file, "            Self::{} {{ .. }} => stack.extend([Work::Event(Event::Leave(\"{}\")), Work::Event(Event::Enter(\"{}\"))]),", node.name, node.name, node.name)?;
+        } else {
+            writeln!(file, "            Self::{} {{ parser, pointer, marker }} => {} {{ parser, pointer, marker }}.push_events(stack),", node.name, node.name)?;
+        }
+    }
+    writeln!(file, "        }}")?;
+    writeln!(file, "    }}")?;
+    writeln!(file)?;
+
+    writeln!(file, "    /// Writes the fingerprint of this node and its descendants to the given")?;
+    writeln!(file, "    /// string (see `ParseResult::fingerprint`).")?;
+    writeln!(file, "    pub(crate) fn write_fingerprint(&self, out: &mut String, depth: usize, label: &str) {{")?;
+    writeln!(file, "        match *self {{")?;
+    for node in &config.nodes {
+        writeln!(file, "            Self::{} {{ parser, pointer, marker }} => {} {{ parser, pointer, marker }}.write_fingerprint(out, depth, label),", node.name, node.name)?;
+    }
+    writeln!(file, "        }}")?;
+    writeln!(file, "    }}")?;
+    writeln!(file)?;
+
+    writeln!(file, "    /// Returns whether the flag with the given name is set on this node, or")?;
+    writeln!(file, "    /// `None` if this kind of node has no such flag. Names are the snake case")?;
+    writeln!(file, "    /// names of the typed flag accessors without the `is_` prefix (e.g.")?;
+    writeln!(file, "    /// `\"safe_navigation\"` for `CallNode::is_safe_navigation`), compared")?;
+    writeln!(file, "    /// case-insensitively.")?;
+    writeln!(file, "    #[must_use]")?;
+    writeln!(file, "    pub fn flag_is_set(&self, name: &str) -> Option<bool> {{")?;
+    writeln!(file, "        match *self {{")?;
+    for node in &config.nodes {
+        if !node.fields.iter().any(|f| matches!(f.field_type, NodeFieldType::Flags)) {
+            continue;
+        }
+
+        writeln!(file, "            Self::{} {{ parser, pointer, marker }} => {} {{ parser, pointer, marker }}.flag_value(name),", node.name, node.name)?;
+    }
+    writeln!(file, "            _ => None,")?;
+    writeln!(file, "        }}")?;
+    writeln!(file, "    }}")?;
+    writeln!(file)?;
+
+    writeln!(file, "    /// Returns the location of the keyword that introduces this node, e.g.")?;
+    writeln!(file, "    /// `def`, `class`, `module`, `if`, `while`, or `return`, so it can be")?;
+    writeln!(file, "    /// highlighted without lexing the source again. For the modifier forms")?;
+    writeln!(file, "    /// (`x if y`, `x while y`) this is the modifier keyword. Returns `None`")?;
+    writeln!(file, "    /// for nodes without a keyword, and for a ternary `if` and its `else`.")?;
+    writeln!(file, "    #[must_use]")?;
+    writeln!(file, "    pub fn leading_keyword_loc(&self) -> Option<Location<'pr>> {{")?;
+    writeln!(file, "        match *self {{")?;
+    for (name, field, keyword) in LEADING_KEYWORDS {
+        let node = config.nodes.iter().find(|node| node.name == *name).unwrap_or_else(|| panic!("unknown node {name}"));
+        let field = node.fields.iter().find(|f| f.name == *field).unwrap_or_else(|| panic!("unknown field {name}#{field}"));
+
+        let mut location = if matches!(field.field_type, NodeFieldType::OptionalLocation) { format!("node.{}()", field.name) } else { format!("Some(node.{}())", field.name) };
+        if let Some(keyword) = keyword {
+            location.push_str(&format!(".filter(|location| location.as_slice() == b\"{}\")", keyword));
+        }
+        writeln!(file, "            Self::{} {{ parser, pointer, marker }} => {{", node.name)?;
+        writeln!(file, "                let node = {} {{ parser, pointer, marker }};", node.name)?;
+        writeln!(file, "                {}", location)?;
+        writeln!(file, "            }},")?;
+    }
+    writeln!(file, "            _ => None,")?;
+    writeln!(file, "        }}")?;
+    writeln!(file, "    }}")?;
+    writeln!(file)?;
+
+    writeln!(file, "    /// Returns the raw pointer to the node allocated by prism.")?;
+    writeln!(file, "    #[must_use]")?;
+    writeln!(file, "    pub(crate) const fn as_ptr(&self) -> *mut pm_node_t {{")?;
+    writeln!(file, "        match *self {{")?;
+    for node in &config.nodes {
+        writeln!(file, "            Self::{} {{ pointer, .. }} => pointer.cast::<pm_node_t>(),", node.name)?;
+    }
+    writeln!(file, "        }}")?;
+    writeln!(file, "    }}")?;
+    writeln!(file)?;
+
+    writeln!(file, "    /// Returns the direct children of this node, in the order of the fields")?;
+    writeln!(file, "    /// that hold them. Missing optional children are skipped.")?;
+    writeln!(file, "    #[must_use]")?;
+    writeln!(file, "    pub fn child_nodes(&self) -> Vec<Node<'pr>> {{")?;
+    writeln!(file, "        match *self {{")?;
+    for node in &config.nodes {
+        let fields = node.fields.iter().filter(|f| matches!(f.field_type, NodeFieldType::Node | NodeFieldType::OptionalNode | NodeFieldType::NodeList)).collect::<Vec<_>>();
+        if fields.is_empty() {
+            continue;
+        }
+
+        // The leading required children go straight into the `vec!`.
+        let leading = fields.iter().take_while(|field| matches!(field.field_type, NodeFieldType::Node)).count();
+        let initial = fields[..leading].iter().map(|field| format!("node.{}(){}", field.name, if field.kind.is_some() { ".as_node()" } else { "" })).collect::<Vec<_>>();
+
+        writeln!(file, "            Self::{} {{ parser, pointer, marker }} => {{", node.name)?;
+        writeln!(file, "                let node = {} {{ parser, pointer, marker }};", node.name)?;
+        if leading == fields.len() {
+            writeln!(file, "                vec![{}]", initial.join(", "))?;
+            writeln!(file, "            }},")?;
+            continue;
+        }
+
+        if leading == 0 {
+            writeln!(file, "                let mut children = Vec::new();")?;
+        } else {
+            writeln!(file, "                let mut children = vec![{}];", initial.join(", "))?;
+        }
+        for field in &fields[leading..] {
+            let as_node = if field.kind.is_some() { ".as_node()" } else { "" };
+            match field.field_type {
+                NodeFieldType::Node => writeln!(file, "                children.push(node.{}(){});", field.name, as_node)?,
+                NodeFieldType::OptionalNode if field.kind.is_some() => writeln!(file, "                children.extend(node.{}().map(|child| child.as_node()));", field.name)?,
+                NodeFieldType::OptionalNode => writeln!(file, "                children.extend(node.{}());", field.name)?,
+                _ => writeln!(file, "                children.extend(node.{}().iter());", field.name)?,
+            }
+        }
+        writeln!(file, "                children")?;
+        writeln!(file, "            }},")?;
+    }
+    writeln!(file, "            _ => Vec::new(),")?;
+    writeln!(file, "        }}")?;
+    writeln!(file, "    }}")?;
+    writeln!(file)?;
+
     for node in &config.nodes {
         writeln!(file, "    /// Returns the node as a `{}`.", node.name)?;
         writeln!(file, "    #[must_use]")?;
-        writeln!(file, "    pub fn as{}(&self) -> Option<{}<'_>> {{", struct_name(&node.name), node.name)?;
+        writeln!(file, "    pub fn as{}(&self) -> Option<{}<'pr>> {{", struct_name(&node.name), node.name)?;
         writeln!(file, "        match *self {{")?;
         writeln!(file, "            Self::{} {{ parser, pointer, marker }} => Some({} {{ parser, pointer, marker }}),", node.name, node.name)?;
         writeln!(file, "            _ => None")?;
@@ -806,6 +1522,9 @@ impl<'pr> Node<'pr> {{
     }
 
     write_visit(&mut file, config)?;
+    writeln!(file)?;
+
+    write_operator_write(&mut file, config)?;
 
     Ok(())
 }
@@ -0,0 +1,112 @@
+//! A cursor for walking up and down a syntax tree without recursion.
+
+use std::marker::PhantomData;
+use std::ptr::NonNull;
+
+use ruby_prism_sys::{pm_node_t, pm_parser_t};
+
+use crate::Node;
+
+/// A cursor over a syntax tree.
+///
+/// The cursor keeps the path from the node it was created at down to the
+/// current node, so moving back up the tree and walking the ancestors of the
+/// current node never needs to search the tree again.
+pub struct Cursor<'pr> {
+    /// The pointer to the associated parser the nodes came from.
+    parser: NonNull<pm_parser_t>,
+
+    /// The raw pointers to the nodes from the root of the cursor down to the
+    /// current node, together with the index of each node within its parent.
+    path: Vec<(*mut pm_node_t, usize)>,
+
+    /// The marker to indicate the lifetime of the pointers.
+    marker: PhantomData<&'pr mut pm_node_t>,
+}
+
+impl<'pr> Cursor<'pr> {
+    /// Creates a new cursor positioned at the given node, which becomes the
+    /// root of the cursor.
+    #[must_use]
+    pub fn new(node: &Node<'pr>) -> Self {
+        Cursor {
+            parser: node.location().parser,
+            path: vec![(node.as_ptr(), 0)],
+            marker: PhantomData,
+        }
+    }
+
+    /// Returns the node the cursor is currently positioned at.
+    #[must_use]
+    pub fn node(&self) -> Node<'pr> {
+        let (pointer, _) = self.path[self.path.len() - 1];
+        Node::new(self.parser, pointer)
+    }
+
+    /// Returns how many levels below the root of the cursor the current node
+    /// is.
+    #[must_use]
+    pub fn depth(&self) -> usize {
+        self.path.len() - 1
+    }
+
+    /// Moves the cursor to the child of the current node at the given index
+    /// (see [`Node::child_nodes`]). Returns false without moving if there is
+    /// no such child.
+    pub fn goto_child(&mut self, index: usize) -> bool {
+        match self.node().child_nodes().get(index) {
+            Some(child) => {
+                self.path.push((child.as_ptr(), index));
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// Moves the cursor to the first child of the current node. Returns false
+    /// without moving if the current node has no children.
+    pub fn goto_first_child(&mut self) -> bool {
+        self.goto_child(0)
+    }
+
+    /// Moves the cursor to the next sibling of the current node. Returns false
+    /// without moving if the current node is the last child of its parent or
+    /// the root of the cursor.
+    pub fn goto_next_sibling(&mut self) -> bool {
+        if self.path.len() < 2 {
+            return false;
+        }
+
+        let Some((_, index)) = self.path.pop() else { return false };
+        if self.goto_child(index + 1) {
+            true
+        } else {
+            self.goto_child(index);
+            false
+        }
+    }
+
+    /// Moves the cursor to the parent of the current node. Returns false
+    /// without moving if the current node is the root of the cursor.
+    pub fn goto_parent(&mut self) -> bool {
+        if self.path.len() < 2 {
+            return false;
+        }
+
+        self.path.pop();
+        true
+    }
+
+    /// Returns the ancestors of the current node, starting with its parent and
+    /// ending at the root of the cursor. The current node itself is not
+    /// included.
+    pub fn ancestors(&self) -> impl Iterator<Item = Node<'pr>> + '_ {
+        self.path[..self.path.len() - 1].iter().rev().map(|&(pointer, _)| Node::new(self.parser, pointer))
+    }
+}
+
+impl std::fmt::Debug for Cursor<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Cursor").field("node", &self.node()).field("depth", &self.depth()).finish()
+    }
+}
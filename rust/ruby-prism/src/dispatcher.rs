@@ -0,0 +1,86 @@
+//! A lookup table of handlers by node type, for evaluators that would
+//! otherwise need one large `match` over every kind of node.
+
+use std::collections::HashMap;
+
+use crate::{Node, NodeType};
+
+/// A handler for one kind of node.
+type Handler<T> = Box<dyn Fn(&Node<'_>) -> T>;
+
+/// A table of handlers keyed by node type, with a default handler for the
+/// types that have none. Created with [`NodeDispatch::builder`].
+///
+/// ```
+/// use ruby_prism::{parse, NodeDispatch, NodeType};
+///
+/// let dispatch = NodeDispatch::builder(|_| "other").on(NodeType::IntegerNode, |_| "integer").on(NodeType::CallNode, |_| "call").build();
+///
+/// let result = parse(b"1");
+/// let root = result.node();
+/// let statement = root.as_program_node().unwrap().statements().body().iter().next().unwrap();
+///
+/// assert_eq!(dispatch.dispatch(&statement), "integer");
+/// assert_eq!(dispatch.dispatch(&root), "other");
+/// ```
+pub struct NodeDispatch<T> {
+    handlers: HashMap<NodeType, Handler<T>>,
+    default: Handler<T>,
+}
+
+impl<T> NodeDispatch<T> {
+    /// Starts building a table whose types without a handler are passed to
+    /// the given default handler.
+    #[must_use]
+    pub fn builder(default: impl Fn(&Node<'_>) -> T + 'static) -> NodeDispatchBuilder<T> {
+        NodeDispatchBuilder {
+            dispatch: Self { handlers: HashMap::new(), default: Box::new(default) },
+        }
+    }
+
+    /// Calls the handler for the type of the given node, or the default
+    /// handler if there is none.
+    #[must_use]
+    pub fn dispatch(&self, node: &Node<'_>) -> T {
+        self.handlers.get(&node.node_type()).unwrap_or(&self.default)(node)
+    }
+}
+
+impl<T> std::fmt::Debug for NodeDispatch<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut types = self.handlers.keys().collect::<Vec<_>>();
+        types.sort_unstable_by_key(|node_type| format!("{node_type:?}"));
+        f.debug_struct("NodeDispatch").field("types", &types).finish_non_exhaustive()
+    }
+}
+
+/// A builder for a [`NodeDispatch`].
+pub struct NodeDispatchBuilder<T> {
+    dispatch: NodeDispatch<T>,
+}
+
+impl<T> NodeDispatchBuilder<T> {
+    /// Sets the handler for the nodes of the given type, as returned by
+    /// [`Node::node_type`]. A later handler for the same type replaces the
+    /// earlier one.
+    #[must_use]
+    pub fn on(mut self, node_type: NodeType, handler: impl Fn(&Node<'_>) -> T + 'static) -> Self {
+        self.dispatch.handlers.insert(node_type, Box::new(handler));
+        self
+    }
+
+    /// Finishes building the table.
+    #[must_use]
+    // This can't be a `const fn`, since the builder can't be dropped at
+    // compile time.
+    #[allow(clippy::missing_const_for_fn)]
+    pub fn build(self) -> NodeDispatch<T> {
+        self.dispatch
+    }
+}
+
+impl<T> std::fmt::Debug for NodeDispatchBuilder<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("NodeDispatchBuilder").field("dispatch", &self.dispatch).finish()
+    }
+}
@@ -0,0 +1,100 @@
+//! A flat stream of events for walking a syntax tree without recursion.
+//!
+//! [`Node::events`] emits the events of a node and its descendants in
+//! traversal order, following this grammar:
+//!
+//! ```text
+//! node   = Enter(name) field* Leave(name)
+//! field  = Field(name) value
+//! value  = node
+//!        | Scalar(Scalar::List(n)) value{n}
+//!        | Scalar(any other scalar)
+//! ```
+//!
+//! Fields are emitted in the order of `config.yml`. A missing optional child
+//! node is emitted as `Scalar(Scalar::Nil)`, and a list of child nodes as
+//! `Scalar(Scalar::List(n))` followed by the `n` nodes.
+
+use crate::{ConstantId, ConstantList, Location, Node};
+
+/// A single value in an [`Event::Scalar`].
+#[derive(Debug)]
+pub enum Scalar<'pr> {
+    /// A missing optional value.
+    Nil,
+
+    /// The start of a list of the given number of child nodes, which follow
+    /// as the next values.
+    List(usize),
+
+    /// A location in the source.
+    Location(Location<'pr>),
+
+    /// A name in the constant pool.
+    Constant(ConstantId<'pr>),
+
+    /// A list of names in the constant pool.
+    ConstantList(ConstantList<'pr>),
+
+    /// An integer field, like the depth of a local variable.
+    Integer(u32),
+
+    /// The lowercase names of the flags that are set.
+    Flags(Vec<&'static str>),
+
+    /// The unescaped bytes of a string field.
+    Bytes(&'pr [u8]),
+}
+
+/// An event emitted by [`Node::events`]. See the module documentation for the
+/// order that events come in.
+#[derive(Debug)]
+pub enum Event<'pr> {
+    /// The start of a node, with the name of its kind (e.g. `"CallNode"`).
+    Enter(&'static str),
+
+    /// The name of the field whose value follows.
+    Field(&'static str),
+
+    /// A value that isn't a node.
+    Scalar(Scalar<'pr>),
+
+    /// The end of a node, with the name of its kind.
+    Leave(&'static str),
+}
+
+/// An entry in the work stack of [`Events`]: either an event that is ready to
+/// be emitted, or a node that still has to be expanded into its events.
+pub(crate) enum Work<'pr> {
+    Event(Event<'pr>),
+    Node(Node<'pr>),
+}
+
+/// An iterator over the events of a node, created by [`Node::events`].
+pub struct Events<'pr> {
+    stack: Vec<Work<'pr>>,
+}
+
+impl<'pr> Iterator for Events<'pr> {
+    type Item = Event<'pr>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.stack.pop()? {
+                Work::Event(event) => return Some(event),
+                Work::Node(node) => node.push_events(&mut self.stack),
+            }
+        }
+    }
+}
+
+impl<'pr> Node<'pr> {
+    /// Returns the events of this node and its descendants in traversal order
+    /// (see the [`events`](crate::events) module for the grammar). The events
+    /// are produced lazily from an explicit stack, so deep trees don't
+    /// overflow the call stack.
+    #[must_use]
+    pub fn events(&self) -> Events<'pr> {
+        Events { stack: vec![Work::Node(*self)] }
+    }
+}
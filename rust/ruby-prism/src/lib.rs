@@ -13,13 +13,30 @@ mod bindings {
     include!(concat!(env!("OUT_DIR"), "/bindings.rs"));
 }
 
+mod cursor;
+mod dispatcher;
+pub mod events;
+#[cfg(feature = "regex")]
+mod regexp;
+#[cfg(feature = "symbols")]
+pub mod symbols;
+
+use std::collections::HashMap;
 use std::ffi::{c_char, CStr};
 use std::marker::PhantomData;
 use std::mem::MaybeUninit;
 use std::ptr::NonNull;
 
 pub use self::bindings::*;
-use ruby_prism_sys::{pm_comment_t, pm_diagnostic_t, pm_node_destroy, pm_node_t, pm_parse, pm_parser_free, pm_parser_init, pm_parser_t};
+pub use self::cursor::Cursor;
+pub use self::dispatcher::{NodeDispatch, NodeDispatchBuilder};
+pub use self::events::{Event, Events, Scalar};
+#[cfg(feature = "regex")]
+pub use self::regexp::RegexBuilderFlags;
+use ruby_prism_sys::{
+    pm_comment_t, pm_diagnostic_list_append, pm_diagnostic_t, pm_magic_comment_t, pm_node_destroy, pm_node_t, pm_parse, pm_parser_free, pm_parser_init, pm_parser_t, pm_source_file_node_t, pm_string_length, pm_string_node_t, pm_string_source, pm_string_t, pm_symbol_node_t, pm_x_string_node_t,
+    PM_ERR_CANNOT_PARSE_EXPRESSION,
+};
 
 /// A diagnostic message that came back from the parser.
 #[derive(Debug)]
@@ -120,6 +137,522 @@ impl<'pr> Iterator for Comments<'pr> {
     }
 }
 
+/// Returns the byte offsets of the start of every line in the source, as
+/// recorded by the parser while lexing.
+fn newline_offsets<'pr>(parser: NonNull<pm_parser_t>) -> &'pr [usize] {
+    unsafe {
+        let list = &(*parser.as_ptr()).newline_list;
+        std::slice::from_raw_parts(list.offsets, list.size)
+    }
+}
+
+/// Returns the line number that the parser considers the first line of the
+/// source.
+fn start_line(parser: NonNull<pm_parser_t>) -> usize {
+    usize::try_from(unsafe { (*parser.as_ptr()).start_line }).unwrap_or(1)
+}
+
+/// Returns the ids and bytes of every constant in the parser's constant pool.
+fn constants<'pr>(parser: NonNull<pm_parser_t>) -> impl Iterator<Item = (u32, &'pr [u8])> {
+    let pool = unsafe { &(*parser.as_ptr()).constant_pool };
+
+    (0..pool.size).map(move |index| {
+        let constant = unsafe { &*pool.constants.add(index as usize) };
+        // Pool ids start at 1, since 0 is reserved for "no constant".
+        (index + 1, unsafe { std::slice::from_raw_parts(constant.start, constant.length) })
+    })
+}
+
+/// Returns the `(line, column)` of the given byte offset by searching the
+/// parser's newline table.
+fn line_column(parser: NonNull<pm_parser_t>, offset: usize) -> (usize, usize) {
+    let newlines = newline_offsets(parser);
+    let line = newlines.partition_point(|&newline| newline <= offset).saturating_sub(1);
+    (start_line(parser) + line, offset - newlines[line])
+}
+
+impl Location<'_> {
+    /// Returns the number of characters in the range, in the encoding of the
+    /// source (which is UTF-8 unless changed with a magic comment). Invalid
+    /// byte sequences count as one character per byte.
+    #[must_use]
+    pub fn char_len(&self) -> usize {
+        let slice = self.as_slice();
+        let encoding = unsafe { (*self.parser.as_ptr()).encoding.as_ref() };
+
+        let Some(char_width) = encoding.filter(|encoding| encoding.multibyte).and_then(|encoding| encoding.char_width) else { return slice.len() };
+
+        let mut count = 0;
+        let mut index = 0;
+
+        while index < slice.len() {
+            // Slices never hold more than `isize::MAX` bytes.
+            let remaining = isize::try_from(slice.len() - index).unwrap_or(isize::MAX);
+            let width = unsafe { char_width(slice[index..].as_ptr(), remaining) };
+            index += width.max(1);
+            count += 1;
+        }
+
+        count
+    }
+
+    /// Returns whether the two locations touch with no text in between, i.e.
+    /// one ends exactly where the other starts. Locations from different
+    /// parsers are never adjacent.
+    #[must_use]
+    pub fn is_adjacent_to(&self, other: &Location<'_>) -> bool {
+        self.parser == other.parser && (self.end == other.start || other.end == self.start)
+    }
+}
+
+/// Collapses the given locations into the fewest ranges covering the same
+/// source.
+///
+/// The ranges are sorted by start offset. Overlapping and adjacent locations
+/// are merged into one, while locations from different parsers are never
+/// merged with each other.
+#[must_use]
+pub fn merge_locations<'pr>(locations: &[Location<'pr>]) -> Vec<Location<'pr>> {
+    let mut sorted = locations.iter().collect::<Vec<_>>();
+    sorted.sort_by_key(|location| (location.parser, location.start, location.end));
+
+    let mut merged: Vec<Location<'pr>> = Vec::with_capacity(sorted.len());
+
+    for location in sorted {
+        match merged.last_mut().filter(|last| last.parser == location.parser) {
+            Some(last) if location.start <= last.end => last.end = last.end.max(location.end),
+            _ => merged.push(*location),
+        }
+    }
+
+    merged
+}
+
+/// A location resolved into both byte offsets and line/column positions.
+///
+/// This is returned by [`ParseResult::detailed_location`]. Lines start at the
+/// parser's start line and columns are 0-indexed byte offsets from the start of
+/// the line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DetailedLocation {
+    /// The byte offset of the start of the location.
+    pub start_offset: usize,
+
+    /// The byte offset of the end of the location.
+    pub end_offset: usize,
+
+    /// The `(line, column)` of the start of the location.
+    pub start: (usize, usize),
+
+    /// The `(line, column)` of the end of the location.
+    pub end: (usize, usize),
+}
+
+/// A reference to a constant, as returned by
+/// [`ParseResult::constant_references`].
+#[derive(Debug)]
+pub struct ConstantReference<'pr> {
+    /// The lexical nesting of classes and modules that the reference appears
+    /// in, outermost first, like Ruby's `Module.nesting` in reverse. Each entry
+    /// is the name as written, so `module Foo; class Bar` gives `["Foo", "Bar"]`
+    /// while `class Foo::Bar` gives `["Foo::Bar"]`. Empty at the top level.
+    pub scope: Vec<String>,
+
+    /// The constant path as written, e.g. `"Bar"`, `"Foo::Bar"`, or `"::Bar"`.
+    pub path: String,
+
+    /// The location of the reference.
+    pub location: Location<'pr>,
+}
+
+/// The namespaces enclosing a node during [`walk_scopes`].
+#[derive(Debug, Default)]
+struct Scope {
+    /// The constant paths of the enclosing classes and modules as written
+    /// (e.g. `"Foo::Bar"` for `class Foo::Bar`), outermost first.
+    names: Vec<String>,
+
+    /// Whether the innermost class body is a `class << self`.
+    singleton: bool,
+}
+
+impl Scope {
+    /// Returns the enclosing namespaces joined with `::`, or an empty string
+    /// at the top level.
+    fn owner(&self) -> String {
+        self.names.join("::")
+    }
+}
+
+/// A visitor for [`walk_scopes`].
+trait ScopeVisit<'pr> {
+    /// Called with each node before its children, along with the namespaces
+    /// around it. A class, module, or singleton class is given the namespaces
+    /// it is defined in, not its own. Returns whether to walk the children.
+    fn visit(&mut self, node: &Node<'pr>, scope: &Scope) -> bool;
+
+    /// Called with each node after its children.
+    fn leave(&mut self, _node: &Node<'pr>) {}
+}
+
+impl<'pr, F: FnMut(&Node<'pr>, &Scope) -> bool> ScopeVisit<'pr> for F {
+    fn visit(&mut self, node: &Node<'pr>, scope: &Scope) -> bool {
+        self(node, scope)
+    }
+}
+
+/// Walks the given node in source order, keeping `scope` up to date.
+///
+/// The superclass of a class, the parent of a namespace's path (`Foo` in
+/// `class Foo::Bar`), and the receiver of `class << x` are walked outside of
+/// the namespace, before it is visited. The last part of a constant path or
+/// a constant path target is only a name, so it is never walked. Only `class << self` makes the body a
+/// singleton one.
+fn walk_scopes<'pr>(node: &Node<'pr>, scope: &mut Scope, visitor: &mut impl ScopeVisit<'pr>) {
+    let namespace = match node {
+        Node::ClassNode { .. } => node.as_class_node().map(|class| {
+            if let Some(superclass) = class.superclass() {
+                walk_scopes(&superclass, scope, visitor);
+            }
+            (Some(class.constant_path()), class.body())
+        }),
+        Node::ModuleNode { .. } => node.as_module_node().map(|module| (Some(module.constant_path()), module.body())),
+        Node::SingletonClassNode { .. } => node.as_singleton_class_node().map(|class| {
+            walk_scopes(&class.expression(), scope, visitor);
+            (None, class.body())
+        }),
+        _ => None,
+    };
+
+    let Some((constant_path, body)) = namespace else {
+        if visitor.visit(node, scope) {
+            let children = match node {
+                Node::ConstantPathNode { .. } => node.as_constant_path_node().and_then(|path| path.parent()).into_iter().collect(),
+                Node::ConstantPathTargetNode { .. } => node.as_constant_path_target_node().and_then(|path| path.parent()).into_iter().collect(),
+                _ => node.child_nodes(),
+            };
+            for child in children {
+                walk_scopes(&child, scope, visitor);
+            }
+        }
+        visitor.leave(node);
+        return;
+    };
+
+    if let Some(parent) = constant_path.as_ref().and_then(Node::as_constant_path_node).and_then(|path| path.parent()) {
+        walk_scopes(&parent, scope, visitor);
+    }
+
+    if visitor.visit(node, scope) {
+        let singleton = node.as_singleton_class_node().is_some_and(|class| class.expression().is_self());
+        let outer = std::mem::replace(&mut scope.singleton, singleton);
+        if let Some(path) = &constant_path {
+            scope.names.push(String::from_utf8_lossy(path.location().as_slice()).into_owned());
+        }
+
+        if let Some(body) = body {
+            walk_scopes(&body, scope, visitor);
+        }
+
+        if constant_path.is_some() {
+            scope.names.pop();
+        }
+        scope.singleton = outer;
+    }
+    visitor.leave(node);
+}
+
+/// Returns the name of a constant read or a constant path (or path target)
+/// made up only of constants, or `None` if the path has a dynamic part (e.g.
+/// `foo::Bar`).
+fn constant_path_name(node: &Node<'_>) -> Option<String> {
+    let (parent, child) = match node {
+        Node::ConstantReadNode { .. } => return node.as_constant_read_node().map(|read| String::from_utf8_lossy(read.name().as_slice()).into_owned()),
+        Node::ConstantPathNode { .. } => node.as_constant_path_node().map(|path| (path.parent(), path.child()))?,
+        Node::ConstantPathTargetNode { .. } => node.as_constant_path_target_node().map(|path| (path.parent(), path.child()))?,
+        _ => return None,
+    };
+    let child = constant_path_name(&child)?;
+
+    match parent {
+        Some(parent) => Some(format!("{}::{child}", constant_path_name(&parent)?)),
+        None => Some(format!("::{child}")),
+    }
+}
+
+/// Collects the constant references in the given node, in source order.
+fn collect_constant_references<'pr>(root: &Node<'pr>) -> Vec<ConstantReference<'pr>> {
+    let mut references = Vec::new();
+    // The paths being assigned to, which are only walked for their parents.
+    let mut targets = Vec::new();
+    walk_scopes(root, &mut Scope::default(), &mut |node: &Node<'pr>, scope: &Scope| {
+        let target = match node {
+            Node::ConstantPathWriteNode { .. } => node.as_constant_path_write_node().map(|node| node.target()),
+            Node::ConstantPathOperatorWriteNode { .. } => node.as_constant_path_operator_write_node().map(|node| node.target()),
+            Node::ConstantPathAndWriteNode { .. } => node.as_constant_path_and_write_node().map(|node| node.target()),
+            Node::ConstantPathOrWriteNode { .. } => node.as_constant_path_or_write_node().map(|node| node.target()),
+            _ => None,
+        };
+        targets.extend(target.map(|target| target.as_node().as_ptr()));
+
+        if !matches!(node, Node::ConstantReadNode { .. } | Node::ConstantPathNode { .. }) || targets.contains(&node.as_ptr()) {
+            return true;
+        }
+
+        // A path with a dynamic parent (`x::Y`) is not a reference, but the
+        // parent may hold references.
+        let Some(path) = constant_path_name(node) else { return true };
+        references.push(ConstantReference {
+            scope: scope.names.clone(),
+            path,
+            location: node.location(),
+        });
+        false
+    });
+    references
+}
+
+/// The method used to load a file, as reported in a [`RequireInfo`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RequireKind {
+    /// `require "json"`, which searches the load path.
+    Require,
+
+    /// `require_relative "foo"`, which is relative to the requiring file.
+    RequireRelative,
+
+    /// `load "foo.rb"`, which loads the file every time it is called.
+    Load,
+
+    /// `autoload :Foo, "foo"`, which requires the file when `Foo` is first
+    /// referenced.
+    Autoload,
+}
+
+/// A call that loads another file, as returned by [`ParseResult::requires`].
+#[derive(Debug)]
+pub struct RequireInfo<'pr> {
+    /// The method that was called.
+    pub kind: RequireKind,
+
+    /// The path that is loaded, e.g. `"json"` or `"./foo"`.
+    pub path: String,
+
+    /// Whether the call is at the top level of the file rather than inside of
+    /// a method, block, class, or module body. Top-level calls inside of
+    /// conditionals (`require "x" if y`) still count as top level.
+    pub top_level: bool,
+
+    /// The location of the whole call.
+    pub location: Location<'pr>,
+}
+
+/// Collects the calls that load other files in the given node into
+/// `requires`.
+fn collect_requires<'pr>(node: &Node<'pr>, top_level: bool, requires: &mut Vec<RequireInfo<'pr>>) {
+    if let Some(call) = node.as_call_node().filter(|call| call.receiver().is_none()) {
+        let (kind, index) = match call.name().as_slice() {
+            b"require" => (Some(RequireKind::Require), 0),
+            b"require_relative" => (Some(RequireKind::RequireRelative), 0),
+            b"load" => (Some(RequireKind::Load), 0),
+            b"autoload" => (Some(RequireKind::Autoload), 1),
+            _ => (None, 0),
+        };
+
+        let path = call.arguments().and_then(|arguments| arguments.arguments().iter().nth(index)).and_then(|argument| argument.as_string_node().and_then(|_| argument.static_string_value()));
+        if let (Some(kind), Some(path)) = (kind, path) {
+            requires.push(RequireInfo {
+                kind,
+                path: String::from_utf8_lossy(&path).into_owned(),
+                top_level,
+                location: node.location(),
+            });
+        }
+    }
+
+    let nested = matches!(node, Node::DefNode { .. } | Node::BlockNode { .. } | Node::LambdaNode { .. } | Node::ClassNode { .. } | Node::ModuleNode { .. } | Node::SingletonClassNode { .. });
+    for child in node.child_nodes() {
+        collect_requires(&child, top_level && !nested, requires);
+    }
+}
+
+/// A method definition, as returned by [`ParseResult::method_definitions`].
+#[derive(Debug)]
+pub struct MethodDef<'pr> {
+    /// The name of the method, e.g. `"to_s"`.
+    pub name: String,
+
+    /// The classes and modules that the method is defined in, joined with
+    /// `::` as written (e.g. `"Foo::Bar"`), or empty at the top level.
+    pub owner: String,
+
+    /// Whether the method is defined on the owner itself rather than its
+    /// instances, either with `def self.foo` or inside of `class << self`.
+    pub singleton: bool,
+
+    /// The location of the whole definition.
+    pub location: Location<'pr>,
+}
+
+/// Collects the method definitions in the given node, in source order.
+fn collect_method_definitions<'pr>(root: &Node<'pr>) -> Vec<MethodDef<'pr>> {
+    let mut definitions = Vec::new();
+    walk_scopes(root, &mut Scope::default(), &mut |node: &Node<'pr>, scope: &Scope| {
+        if let Some(def) = node.as_def_node() {
+            definitions.push(MethodDef {
+                name: String::from_utf8_lossy(def.name().as_slice()).into_owned(),
+                owner: scope.owner(),
+                singleton: scope.singleton || def.receiver().is_some_and(|receiver| receiver.is_self()),
+                location: node.location(),
+            });
+        }
+        true
+    });
+    definitions
+}
+
+/// Pushes the path from `node` down to the node with the given pointer onto
+/// `path`, excluding the target itself. Only children whose location covers
+/// the target are searched. Returns false if the target was not found.
+fn find_path<'pr>(node: Node<'pr>, target: *mut pm_node_t, start: usize, end: usize, path: &mut Vec<Node<'pr>>) -> bool {
+    if node.as_ptr() == target {
+        return true;
+    }
+
+    let children = node.child_nodes();
+    path.push(node);
+
+    for child in children {
+        let location = child.location();
+        if location.start_offset() <= start && end <= location.end_offset() && find_path(child, target, start, end, path) {
+            return true;
+        }
+    }
+
+    path.pop();
+    false
+}
+
+/// An id for a node derived from its path from the root, as assigned by
+/// [`ParseResult::assign_ids`].
+///
+/// The same path gives the same id in every parse, so ids can be compared
+/// across parses of different versions of a file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NodeId(u64);
+
+impl NodeId {
+    /// The id of the root node.
+    const ROOT: Self = Self(0xcbf2_9ce4_8422_2325);
+
+    /// Returns the id of the child of this node with the given kind that is
+    /// the given number of children of that kind into the parent. The id is
+    /// mixed with 64-bit FNV-1a, which doesn't depend on the platform or the
+    /// run, unlike the standard library's hasher.
+    fn child(self, kind: &str, ordinal: usize) -> Self {
+        let bytes = kind.bytes().chain([0xff]).chain(ordinal.to_le_bytes());
+        Self(bytes.fold(self.0, |hash, byte| (hash ^ u64::from(byte)).wrapping_mul(0x0000_0100_0000_01b3)))
+    }
+
+    /// Returns the raw value of the id, e.g. for storing it in a cache.
+    #[must_use]
+    pub const fn value(self) -> u64 {
+        self.0
+    }
+}
+
+/// The visibility of a method, as inferred by
+/// [`ParseResult::method_visibilities`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Visibility {
+    /// The method can be called from anywhere.
+    Public,
+
+    /// The method can only be called by instances of the class or its
+    /// subclasses.
+    Protected,
+
+    /// The method can only be called without an explicit receiver.
+    Private,
+}
+
+impl Visibility {
+    /// Returns the visibility set by a call to the method with the given name,
+    /// if it is one of `public`, `protected`, or `private`.
+    const fn from_modifier(name: &[u8]) -> Option<Self> {
+        match name {
+            b"public" => Some(Self::Public),
+            b"protected" => Some(Self::Protected),
+            b"private" => Some(Self::Private),
+            _ => None,
+        }
+    }
+}
+
+/// Infers the visibility of the methods defined in the given node. The
+/// statements of each class, module, and `class << self` body are walked in
+/// order, tracking the visibility set by bare `private`/`protected`/`public`
+/// calls.
+fn collect_method_visibilities(root: &Node<'_>) -> HashMap<(usize, usize), Visibility> {
+    let mut visibilities = HashMap::new();
+    walk_scopes(root, &mut Scope::default(), &mut |node: &Node<'_>, _: &Scope| {
+        let body = match node {
+            Node::ClassNode { .. } => node.as_class_node().and_then(|node| node.body()),
+            Node::ModuleNode { .. } => node.as_module_node().and_then(|node| node.body()),
+            Node::SingletonClassNode { .. } => node.as_singleton_class_node().and_then(|node| node.body()),
+            _ => None,
+        };
+
+        if let Some(statements) = body.as_ref().and_then(Node::as_statements_node) {
+            collect_body_visibilities(&statements, &mut visibilities);
+        }
+        true
+    });
+    visibilities
+}
+
+/// Infers the visibility of the methods defined directly in the statements
+/// of a class, module, or `class << self` body.
+fn collect_body_visibilities(statements: &StatementsNode<'_>, visibilities: &mut HashMap<(usize, usize), Visibility>) {
+    let mut current = Visibility::Public;
+    // The methods defined so far in this body, by name.
+    let mut defined: Vec<(&[u8], (usize, usize))> = Vec::new();
+
+    for statement in statements.body().iter() {
+        let modifier = statement
+            .as_call_node()
+            .filter(|call| call.receiver().is_none() && call.block().is_none())
+            .and_then(|call| Some((Visibility::from_modifier(call.name().as_slice())?, call.arguments())));
+
+        match (modifier, statement.as_def_node()) {
+            (Some((visibility, None)), _) => current = visibility,
+            (Some((visibility, Some(arguments))), _) => {
+                for argument in arguments.arguments().iter() {
+                    if let Some(def) = argument.as_def_node() {
+                        let span = (def.location().start_offset(), def.location().end_offset());
+                        defined.push((def.name().as_slice(), span));
+                        visibilities.insert(span, visibility);
+                    } else if let Some(name) = argument.static_string_value() {
+                        for (_, span) in defined.iter().filter(|(defined, _)| *defined == name.as_slice()) {
+                            visibilities.insert(*span, visibility);
+                        }
+                    }
+                }
+            },
+            (None, Some(def)) => {
+                let span = (def.location().start_offset(), def.location().end_offset());
+                // `def self.foo` is not affected by the visibility modifiers.
+                let visibility = if def.receiver().is_some() { Visibility::Public } else { current };
+                if def.receiver().is_none() {
+                    defined.push((def.name().as_slice(), span));
+                }
+                visibilities.insert(span, visibility);
+            },
+            (None, None) => {},
+        }
+    }
+}
+
 /// The result of parsing a source string.
 #[derive(Debug)]
 pub struct ParseResult<'pr> {
@@ -141,6 +674,128 @@ impl<'pr> ParseResult<'pr> {
         unsafe { (*self.parser.as_ptr()).frozen_string_literal }
     }
 
+    /// Returns the `#!` line at the very start of the file (after an optional
+    /// byte order mark), without the trailing newline.
+    #[must_use]
+    pub fn shebang(&self) -> Option<Location<'_>> {
+        let bom = if self.source.starts_with(b"\xEF\xBB\xBF") { 3 } else { 0 };
+        self.comments().next().map(|comment| comment.location()).filter(|location| location.start_offset() == bom && location.as_slice().starts_with(b"#!"))
+    }
+
+    /// Returns the value of the `frozen_string_literal` magic comment, or
+    /// `None` if the file has no such comment with a `true` or `false` value.
+    /// Like in Ruby, only comments before the first line of code count, and
+    /// the last one wins. See also [`ParseResult::frozen_string_literals`].
+    #[must_use]
+    pub fn frozen_string_literal(&self) -> Option<bool> {
+        let code_start = self.node().as_program_node().and_then(|program| program.statements().body().iter().next()).map_or(usize::MAX, |node| node.location().start_offset());
+        let is_frozen_key = |key: &[u8]| key.len() == 21 && key.iter().zip(b"frozen_string_literal").all(|(&byte, &expected)| byte.to_ascii_lowercase() == expected || (byte == b'-' && expected == b'_'));
+
+        self.magic_comments()
+            .filter(|(key, _)| is_frozen_key(key) && key.as_ptr() as usize - self.source.as_ptr() as usize <= code_start)
+            .filter_map(|(_, value)| {
+                if value.get(..4).is_some_and(|prefix| prefix.eq_ignore_ascii_case(b"true")) {
+                    Some(true)
+                } else if value.get(..5).is_some_and(|prefix| prefix.eq_ignore_ascii_case(b"false")) {
+                    Some(false)
+                } else {
+                    None
+                }
+            })
+            .last()
+    }
+
+    /// Returns the name of the encoding of the source, e.g. `"UTF-8"`, which
+    /// is either the default or the one set by an `encoding` magic comment.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the name is not valid UTF-8, which never happens for the
+    /// encodings that prism knows about.
+    #[must_use]
+    pub fn encoding_name(&self) -> &str {
+        unsafe {
+            let name = (*(*self.parser.as_ptr()).encoding).name;
+            CStr::from_ptr(name).to_str().expect("prism encoding names are ASCII")
+        }
+    }
+
+    /// Looks up the constant pool ids of the given names, so that names in the
+    /// tree can be compared against them with [`ConstantId::id`] instead of
+    /// comparing bytes. A name maps to `None` if it does not appear anywhere in
+    /// the source. The ids are local to this parse result, so the map has to be
+    /// rebuilt for every parse.
+    #[must_use]
+    pub fn intern_names<'n>(&self, names: &[&'n str]) -> HashMap<&'n str, Option<u32>> {
+        let mut ids = HashMap::with_capacity(names.len());
+        for (id, bytes) in constants(self.parser) {
+            ids.entry(bytes).or_insert(id);
+        }
+
+        names.iter().map(|&name| (name, ids.get(name.as_bytes()).copied())).collect()
+    }
+
+    /// Returns the documentation of the given node: the text of the block of
+    /// comments directly above it, with no blank lines in between. The `#` of
+    /// each line comment is stripped along with one following space, and the
+    /// `=begin`/`=end` lines of an embedded document are dropped. The lines
+    /// are joined with `\n`. Comments that share a line with code are not
+    /// part of the documentation.
+    #[must_use]
+    pub fn doc_comment(&self, node: &Node<'_>) -> Option<String> {
+        let start = node.location().start_offset();
+        let mut line = line_column(self.parser, start).0;
+        let comments = self.comments().filter(|comment| comment.location().end_offset() <= start).collect::<Vec<_>>();
+
+        let mut lines = Vec::new();
+
+        for comment in comments.iter().rev() {
+            let location = comment.location();
+            let (first_line, column) = line_column(self.parser, location.start_offset());
+            let last_line = line_column(self.parser, location.end_offset().saturating_sub(1)).0;
+            let indentation = &self.source[location.start_offset() - column..location.start_offset()];
+
+            if last_line + 1 != line || !indentation.iter().all(u8::is_ascii_whitespace) {
+                break;
+            }
+
+            let text = comment.text();
+            if text.starts_with(b"=begin") {
+                let body = text.split(|&byte| byte == b'\n').skip(1).take_while(|line| !line.starts_with(b"=end"));
+                lines.extend(body.collect::<Vec<_>>().into_iter().rev());
+            } else {
+                let text = &text[1..];
+                lines.push(text.strip_prefix(b" ").unwrap_or(text));
+            }
+
+            line = first_line;
+        }
+
+        if lines.is_empty() {
+            return None;
+        }
+
+        let lines = lines.iter().rev().map(|line| String::from_utf8_lossy(line.strip_suffix(b"\r").unwrap_or(line))).collect::<Vec<_>>();
+        Some(lines.join("\n"))
+    }
+
+    /// Returns the keys and values of the magic comments, as written in the
+    /// source.
+    fn magic_comments(&self) -> impl Iterator<Item = (&'pr [u8], &'pr [u8])> + '_ {
+        let mut comment = unsafe { (*self.parser.as_ptr()).magic_comment_list.head.cast::<pm_magic_comment_t>() };
+
+        std::iter::from_fn(move || {
+            let current = unsafe { comment.as_ref()? };
+            comment = current.node.next.cast::<pm_magic_comment_t>();
+
+            unsafe {
+                let key = std::slice::from_raw_parts(current.key_start, current.key_length as usize);
+                let value = std::slice::from_raw_parts(current.value_start, current.value_length as usize);
+                Some((key, value))
+            }
+        })
+    }
+
     /// Returns a slice of the source string that was parsed using the given
     /// location range.
     ///
@@ -156,6 +811,206 @@ impl<'pr> ParseResult<'pr> {
         &self.source[start..end]
     }
 
+    /// Converts the given byte offsets into `(line, column)` pairs. Lines start
+    /// at the parser's start line (1 by default) and columns are 0-indexed byte
+    /// offsets from the start of the line.
+    ///
+    /// The queries are sorted and resolved in a single pass over the newline
+    /// table, which is cheaper than a binary search per offset when converting
+    /// many offsets at once. The returned positions are in the same order as
+    /// the given offsets.
+    #[must_use]
+    pub fn positions(&self, offsets: &[usize]) -> Vec<(usize, usize)> {
+        let newlines = newline_offsets(self.parser);
+        let start_line = start_line(self.parser);
+
+        let mut order = (0..offsets.len()).collect::<Vec<_>>();
+        order.sort_unstable_by_key(|&index| offsets[index]);
+
+        let mut positions = vec![(0, 0); offsets.len()];
+        let mut line = 0;
+
+        for index in order {
+            let offset = offsets[index];
+            while line + 1 < newlines.len() && newlines[line + 1] <= offset {
+                line += 1;
+            }
+            positions[index] = (start_line + line, offset - newlines[line]);
+        }
+
+        positions
+    }
+
+    /// Returns every constant referenced in the source (each `ConstantReadNode`
+    /// and `ConstantPathNode`, outermost path only) together with the lexical
+    /// class and module nesting it appears in, in source order. The names of
+    /// classes and modules being defined are not references, but their
+    /// superclasses and the namespaces they are defined under are. Likewise,
+    /// constants being assigned to (`X = 1`, `Foo::Y += 1`, or `A::B` in
+    /// `A::B, c = 1, 2`) are not references, but the namespaces they are
+    /// assigned under are. Reopening a namespace gives the same scope as the
+    /// first definition.
+    #[must_use]
+    pub fn constant_references(&self) -> Vec<ConstantReference<'_>> {
+        collect_constant_references(&self.node())
+    }
+
+    /// Returns the inferred visibility of every method defined directly in a
+    /// class, module, or `class << self` body, keyed by the `(start, end)`
+    /// byte offsets of its `def`. Both the section form (`private` on its own
+    /// line) and the argument forms (`private def foo` and `private :foo`) are
+    /// understood. Methods defined at the top level or inside other methods
+    /// are not included.
+    #[must_use]
+    pub fn method_visibilities(&self) -> HashMap<(usize, usize), Visibility> {
+        collect_method_visibilities(&self.node())
+    }
+
+    /// Returns a canonical text form of the tree, for diffing against the
+    /// output of the same serializer written against the Ruby prism gem.
+    ///
+    /// There is one line per node, in pre-order, indented by two spaces per
+    /// level. A line is made up of, separated by single spaces:
+    ///
+    /// * for every node but the root, the name of the field that holds it
+    ///   followed by `:`, with `[index]` after the name for list fields, e.g.
+    ///   `receiver:` or `arguments[0]:`
+    /// * the node's name, e.g. `CallNode`
+    /// * the node's byte offsets as `start..end`
+    /// * every non-node field in the order of `config.yml`, as `name=value`
+    ///
+    /// Locations are written as `start..end`, integers in decimal, constants
+    /// and strings as double quoted bytes (see below), constant lists as
+    /// `[...]` of quoted names separated by `,`, and flags as `[...]` of the
+    /// lowercase names of the set flags separated by `,`. Missing optional
+    /// values are written as `nil`, and missing optional children have no
+    /// line. Inside of quotes, `"` and `\` are escaped with a backslash and
+    /// bytes outside of printable ASCII are written as `\xNN` with uppercase
+    /// hex digits. Every line ends with `\n`.
+    ///
+    /// For example, `foo(1)` gives:
+    ///
+    /// ```text
+    /// ProgramNode 0..6 locals=[]
+    ///   statements: StatementsNode 0..6
+    ///     body[0]: CallNode 0..6 flags=[ignore_visibility] call_operator_loc=nil name="foo" message_loc=0..3 opening_loc=3..4 closing_loc=5..6
+    ///       arguments: ArgumentsNode 4..5 flags=[]
+    ///         arguments[0]: IntegerNode 4..5 flags=[decimal]
+    /// ```
+    #[must_use]
+    pub fn fingerprint(&self) -> String {
+        let mut out = String::new();
+        self.node().write_fingerprint(&mut out, 0, "");
+        out
+    }
+
+    /// Returns every `require`, `require_relative`, `load`, and `autoload`
+    /// call without a receiver whose path is a plain string literal, in
+    /// source order. Calls with a dynamic path (`require "#{dir}/x"` or
+    /// `require path`) are skipped, since they can't be resolved statically.
+    #[must_use]
+    pub fn requires(&self) -> Vec<RequireInfo<'_>> {
+        let mut requires = Vec::new();
+        collect_requires(&self.node(), true, &mut requires);
+        requires
+    }
+
+    /// Assigns every node an id derived from its path from the root, keyed by
+    /// the start and end byte offsets and the kind of the node.
+    ///
+    /// Each step in the path is the kind of the child along with how many
+    /// earlier siblings have the same kind, so `foo; bar` and `x = 1; foo;
+    /// bar` give the calls the same ids, since adding the assignment doesn't
+    /// change how many calls come before them. Matching the ids of two parses
+    /// of an edited file therefore pairs up the nodes that kept their place in
+    /// the tree, even if their offsets moved.
+    ///
+    /// The heuristic is positional, so it has limits: inserting a node before
+    /// siblings of the same kind shifts the ids of all of them, a node that
+    /// changes kind (e.g. `foo` becoming `foo.bar`) gets a new id along with
+    /// everything below it, and moving code into another method or block
+    /// changes the path entirely. After large edits, few ids will match.
+    #[must_use]
+    pub fn assign_ids(&self) -> HashMap<(usize, usize, NodeType), NodeId> {
+        let mut ids = HashMap::new();
+        let mut stack = vec![(self.node(), NodeId::ROOT)];
+
+        while let Some((node, id)) = stack.pop() {
+            let location = node.location();
+            ids.insert((location.start_offset(), location.end_offset(), node.node_type()), id);
+
+            let mut ordinals = HashMap::new();
+            for child in node.child_nodes() {
+                let ordinal = ordinals.entry(child.node_type()).or_insert(0);
+                stack.push((child, id.child(child.kind_name(), *ordinal)));
+                *ordinal += 1;
+            }
+        }
+
+        ids
+    }
+
+    /// Returns every method definition in source order, along with the
+    /// classes and modules it is defined in. A `class << self` body makes
+    /// the methods in it singleton methods of the enclosing class or module.
+    #[must_use]
+    pub fn method_definitions(&self) -> Vec<MethodDef<'_>> {
+        collect_method_definitions(&self.node())
+    }
+
+    /// Returns the ancestors of the given node, starting with its parent and
+    /// ending at the root. Returns an empty list for the root itself, or if
+    /// the node doesn't belong to this parse result. The tree is searched from
+    /// the root, only descending into nodes that cover the given node.
+    #[must_use]
+    pub fn ancestors_of(&self, node: &Node<'_>) -> Vec<Node<'_>> {
+        let location = node.location();
+        let mut path = Vec::new();
+
+        if find_path(self.node(), node.as_ptr(), location.start_offset(), location.end_offset(), &mut path) {
+            path.reverse();
+            path
+        } else {
+            Vec::new()
+        }
+    }
+
+    /// Returns the nearest method definition that the given node is inside of.
+    #[must_use]
+    pub fn enclosing_method(&self, node: &Node<'_>) -> Option<DefNode<'_>> {
+        self.ancestors_of(node).iter().find_map(Node::as_def_node)
+    }
+
+    /// Returns the nearest class, module, or singleton class (`class << self`)
+    /// that the given node is inside of.
+    #[must_use]
+    pub fn enclosing_namespace(&self, node: &Node<'_>) -> Option<Node<'_>> {
+        self.ancestors_of(node).into_iter().find(|ancestor| matches!(ancestor, Node::ClassNode { .. } | Node::ModuleNode { .. } | Node::SingletonClassNode { .. }))
+    }
+
+    /// Resolves the given location into its offsets and line/column positions
+    /// at once. The start line is found with a single search of the newline
+    /// table, and the end line by scanning forward from there, which is cheaper
+    /// than converting both ends separately.
+    #[must_use]
+    pub fn detailed_location(&self, location: &Location<'_>) -> DetailedLocation {
+        let newlines = newline_offsets(self.parser);
+        let start_line = start_line(self.parser);
+
+        let start_offset = location.start_offset();
+        let end_offset = location.end_offset();
+
+        let start = newlines.partition_point(|&newline| newline <= start_offset).saturating_sub(1);
+        let end = start + newlines[start..].partition_point(|&newline| newline <= end_offset).saturating_sub(1);
+
+        DetailedLocation {
+            start_offset,
+            end_offset,
+            start: (start_line + start, start_offset - newlines[start]),
+            end: (start_line + end, end_offset - newlines[end]),
+        }
+    }
+
     /// Returns an iterator that can be used to iterate over the errors in the
     /// parse result.
     #[must_use]
@@ -203,6 +1058,30 @@ impl<'pr> ParseResult<'pr> {
     pub fn node(&self) -> Node<'_> {
         Node::new(self.parser, self.node.as_ptr())
     }
+
+    /// Returns the statements at the top level of the program.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the root node is not a program node, which never happens for
+    /// the trees that prism returns.
+    #[must_use]
+    pub fn top_level_statements(&self) -> NodeList<'_> {
+        self.node().as_program_node().expect("the root node should be a program node").statements().body()
+    }
+
+    /// Returns whether the program runs code at the top level, as a script
+    /// does, rather than only defining things, as a library does. Classes,
+    /// modules, methods, constant assignments, and receiverless `require` and
+    /// `require_relative` calls count as definitions.
+    #[must_use]
+    pub fn has_top_level_code(&self) -> bool {
+        self.top_level_statements().iter().any(|statement| match statement {
+            Node::ClassNode { .. } | Node::ModuleNode { .. } | Node::DefNode { .. } | Node::ConstantWriteNode { .. } | Node::ConstantPathWriteNode { .. } => false,
+            Node::CallNode { .. } => !statement.as_call_node().is_some_and(|call| call.receiver().is_none() && matches!(call.name().as_slice(), b"require" | b"require_relative")),
+            _ => true,
+        })
+    }
 }
 
 impl<'pr> Drop for ParseResult<'pr> {
@@ -239,539 +1118,2698 @@ pub fn parse(source: &[u8]) -> ParseResult<'_> {
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::parse;
+/// Parses the given source string as a single expression, for snippets that
+/// are not complete programs (e.g. a method body pulled out of its context).
+///
+/// The source is parsed as a program, so the tree returned by
+/// [`ParseResult::node`] is still a `ProgramNode`. The difference from
+/// [`parse`] is in the errors: if the source holds more than one statement,
+/// everything after the first one is reported as a single "unexpected trailing
+/// content" error spanning the extra statements, and an empty source is
+/// reported as an "expected an expression" error. Incomplete input, such as a
+/// missing `end`, is reported by the parser exactly as it would be by `parse`.
+///
+/// # Panics
+///
+/// Panics if the parser fails to initialize.
+///
+#[must_use]
+pub fn parse_expression(source: &[u8]) -> ParseResult<'_> {
+    let result = parse(source);
+
+    let (start, end, message) = {
+        let root = result.node();
+        let program = root.as_program_node().expect("the root node should be a program node");
+        let statements = program.statements().body().iter().collect::<Vec<_>>();
+
+        match statements.as_slice() {
+            [] => {
+                let location = program.location();
+                (location.start, location.start, b"expected an expression\0".as_slice())
+            },
+            [_] => return result,
+            [_, second, rest @ ..] => {
+                let last = rest.last().unwrap_or(second);
+                (second.location().start, last.location().end, b"unexpected trailing content after the expression\0".as_slice())
+            },
+        }
+    };
 
-    #[test]
-    fn comments_test() {
-        let source = "# comment 1\n# comment 2\n# comment 3\n";
-        let result = parse(source.as_ref());
+    unsafe {
+        let errors = &mut (*result.parser.as_ptr()).error_list;
 
-        for comment in result.comments() {
-            let text = std::str::from_utf8(comment.text()).unwrap();
-            assert!(text.starts_with("# comment"));
+        // Prism has no diagnostic for these, so the message of the one that is
+        // appended is replaced. The message is static, so the diagnostic must
+        // not own it, which is already the case for the ones appended here.
+        if pm_diagnostic_list_append(errors, start, end, PM_ERR_CANNOT_PARSE_EXPRESSION) {
+            (*errors.tail.cast::<pm_diagnostic_t>()).message = message.as_ptr().cast::<c_char>();
         }
     }
 
-    #[test]
-    fn location_test() {
-        let source = "111 + 222 + 333";
-        let result = parse(source.as_ref());
+    result
+}
 
-        let node = result.node();
-        let node = node.as_program_node().unwrap().statements().body().iter().next().unwrap();
-        let node = node.as_call_node().unwrap().receiver().unwrap();
-        let plus = node.as_call_node().unwrap();
-        let node = plus.arguments().unwrap().arguments().iter().next().unwrap();
+impl CaseNode<'_> {
+    /// Returns the number of `when` clauses, not counting the `else` clause.
+    #[must_use]
+    pub fn branch_count(&self) -> usize {
+        self.conditions().iter().count()
+    }
 
-        let location = node.as_integer_node().unwrap().location();
-        let slice = std::str::from_utf8(result.as_slice(&location)).unwrap();
+    /// Returns whether the case expression has an `else` clause.
+    #[must_use]
+    pub fn has_else(&self) -> bool {
+        self.consequent().is_some()
+    }
+}
 
-        assert_eq!(slice, "222");
-        assert_eq!(6, location.start_offset());
-        assert_eq!(9, location.end_offset());
+/// Implements the class variable name helpers for each of the given nodes.
+///
+/// Class variables are not resolved lexically like local variables: `@@x` is
+/// looked up on the class (or module) of the lexical scope it appears in, then
+/// up that class's ancestors, and it is shared with every subclass. A read and
+/// a write of `@@x` in two different classes can therefore refer to the same
+/// variable, so analyses should key on the name together with the enclosing
+/// class rather than the name alone.
+macro_rules! class_variable_names {
+    ($($node:ident),*) => {
+        $(
+            impl<'pr> $node<'pr> {
+                /// Returns the name of the class variable including the leading
+                /// `@@`, e.g. `"@@count"`.
+                ///
+                /// # Panics
+                ///
+                /// Panics if the name is not valid UTF-8.
+                #[must_use]
+                pub fn name_str(&self) -> &'pr str {
+                    std::str::from_utf8(self.name().as_slice()).expect("class variable names should be valid UTF-8")
+                }
+
+                /// Returns the name of the class variable without the leading
+                /// `@@`, e.g. `"count"`.
+                ///
+                /// # Panics
+                ///
+                /// Panics if the name is not valid UTF-8.
+                #[must_use]
+                pub fn bare_name_str(&self) -> &'pr str {
+                    let name = self.name_str();
+                    name.strip_prefix("@@").unwrap_or(name)
+                }
+            }
+        )*
+    };
+}
 
-        let recv_loc = plus.receiver().unwrap().location();
-        assert_eq!(recv_loc.as_slice(), b"111");
-        assert_eq!(0, recv_loc.start_offset());
-        assert_eq!(3, recv_loc.end_offset());
+class_variable_names!(ClassVariableAndWriteNode, ClassVariableOperatorWriteNode, ClassVariableOrWriteNode, ClassVariableReadNode, ClassVariableTargetNode, ClassVariableWriteNode);
 
-        let joined = recv_loc.join(&location).unwrap();
-        assert_eq!(joined.as_slice(), b"111 + 222");
+impl DefNode<'_> {
+    /// Returns the number of `yield` expressions in the body of the method.
+    /// Yields inside of blocks and lambdas count, since they still yield to
+    /// the block of this method, but yields inside of nested methods, classes,
+    /// and modules don't.
+    #[must_use]
+    pub fn yield_count(&self) -> usize {
+        fn count(node: &Node<'_>) -> usize {
+            match node {
+                Node::YieldNode { .. } => 1 + node.child_nodes().iter().map(count).sum::<usize>(),
+                Node::DefNode { .. } | Node::ClassNode { .. } | Node::ModuleNode { .. } | Node::SingletonClassNode { .. } => 0,
+                _ => node.child_nodes().iter().map(count).sum(),
+            }
+        }
 
-        let not_joined = location.join(&recv_loc);
-        assert!(not_joined.is_none());
+        self.body().map_or(0, |body| count(&body))
+    }
 
-        {
-            let result = parse(source.as_ref());
-            let node = result.node();
-            let node = node.as_program_node().unwrap().statements().body().iter().next().unwrap();
-            let node = node.as_call_node().unwrap().receiver().unwrap();
-            let plus = node.as_call_node().unwrap();
-            let node = plus.arguments().unwrap().arguments().iter().next().unwrap();
+    /// Returns the arity of the method (see [`ParametersNode::arity`]), which
+    /// is all zeroes for a method without parameters.
+    #[must_use]
+    pub fn arity(&self) -> Arity {
+        self.parameters().map(|parameters| parameters.arity()).unwrap_or_default()
+    }
 
-            let location = node.as_integer_node().unwrap().location();
-            let not_joined = recv_loc.join(&location);
-            assert!(not_joined.is_none());
+    /// Returns whether this is an endless method definition
+    /// (`def square(x) = x * x`), which has an `=` in place of a body ending
+    /// in `end`.
+    #[must_use]
+    pub fn is_endless(&self) -> bool {
+        self.equal_loc().is_some() && self.end_keyword_loc().is_none()
+    }
+}
 
-            let not_joined = location.join(&recv_loc);
-            assert!(not_joined.is_none());
-        }
+/// The arity of a method or block, as returned by [`ParametersNode::arity`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Arity {
+    /// The number of required positional parameters, including the ones after
+    /// a rest parameter.
+    pub required: usize,
 
-        let location = node.location();
-        let slice = std::str::from_utf8(result.as_slice(&location)).unwrap();
+    /// The number of optional positional parameters (`b = 1`).
+    pub optional: usize,
 
-        assert_eq!(slice, "222");
+    /// Whether there is a rest parameter (`*args` or `*`).
+    pub rest: bool,
 
-        let slice = std::str::from_utf8(location.as_slice()).unwrap();
+    /// The number of required keyword parameters (`c:`).
+    pub required_keywords: usize,
 
-        assert_eq!(slice, "222");
-    }
+    /// The number of optional keyword parameters (`d: 1`).
+    pub optional_keywords: usize,
 
-    #[test]
-    fn visitor_test() {
-        use super::{visit_interpolated_regular_expression_node, visit_regular_expression_node, InterpolatedRegularExpressionNode, RegularExpressionNode, Visit};
+    /// Whether there is a keyword rest parameter (`**opts` or `**`). This is
+    /// false for `**nil`, which accepts no keywords at all.
+    pub keyword_rest: bool,
 
-        struct RegularExpressionVisitor {
-            count: usize,
-        }
+    /// Whether there is a block parameter (`&blk` or `&`).
+    pub block: bool,
+}
 
-        impl Visit<'_> for RegularExpressionVisitor {
-            fn visit_interpolated_regular_expression_node(&mut self, node: &InterpolatedRegularExpressionNode<'_>) {
-                self.count += 1;
-                visit_interpolated_regular_expression_node(self, node);
-            }
+/// The breakdown of the arguments of a call, as returned by
+/// [`ArgumentsNode::classify`] and [`CallNode::classify_arguments`]. Positions
+/// are indices into [`ArgumentsNode::arguments`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ArgumentShape {
+    /// The positions of the positional arguments (`foo(a, b)`).
+    pub positional: Vec<usize>,
 
-            fn visit_regular_expression_node(&mut self, node: &RegularExpressionNode<'_>) {
-                self.count += 1;
-                visit_regular_expression_node(self, node);
-            }
-        }
+    /// The positions of the splat arguments (`foo(*a)`).
+    pub splats: Vec<usize>,
 
-        let source = "# comment 1\n# comment 2\nmodule Foo; class Bar; /abc #{/def/}/; end; end";
-        let result = parse(source.as_ref());
+    /// The position of the bare keyword arguments (`foo(a: 1, **b)`), which
+    /// are grouped into a single `KeywordHashNode`.
+    pub keyword_hash: Option<usize>,
 
-        let mut visitor = RegularExpressionVisitor { count: 0 };
-        visitor.visit(&result.node());
+    /// The number of `key: value` pairs in the keyword arguments.
+    pub keywords: usize,
 
-        assert_eq!(visitor.count, 2);
-    }
+    /// The number of double splats (`**a`) in the keyword arguments.
+    pub double_splats: usize,
 
-    #[test]
-    fn node_upcast_test() {
-        use super::Node;
+    /// The position of the argument forwarding (`foo(...)`), if any.
+    pub forwarding: Option<usize>,
 
-        let source = "module Foo; end";
-        let result = parse(source.as_ref());
+    /// Whether a block argument (`foo(&a)` or `foo(&)`) is passed.
+    pub block: bool,
+}
 
-        let node = result.node();
-        let upcast_node = node.as_program_node().unwrap().as_node();
-        assert!(matches!(upcast_node, Node::ProgramNode { .. }));
+impl ArgumentsNode<'_> {
+    /// Classifies the arguments by kind in a single pass. Block arguments are
+    /// stored on the call rather than in the arguments, so `block` is only set
+    /// by [`CallNode::classify_arguments`].
+    #[must_use]
+    pub fn classify(&self) -> ArgumentShape {
+        let mut shape = ArgumentShape::default();
+
+        for (index, argument) in self.arguments().iter().enumerate() {
+            match argument {
+                Node::SplatNode { .. } => shape.splats.push(index),
+                Node::ForwardingArgumentsNode { .. } => shape.forwarding = Some(index),
+                Node::BlockArgumentNode { .. } => shape.block = true,
+                Node::KeywordHashNode { .. } => {
+                    shape.keyword_hash = Some(index);
+                    for element in argument.as_keyword_hash_node().into_iter().flat_map(|hash| hash.elements().iter()) {
+                        if matches!(element, Node::AssocSplatNode { .. }) {
+                            shape.double_splats += 1;
+                        } else {
+                            shape.keywords += 1;
+                        }
+                    }
+                },
+                _ => shape.positional.push(index),
+            }
+        }
 
-        let node = node.as_program_node().unwrap().statements().body().iter().next().unwrap();
-        let upcast_node = node.as_module_node().unwrap().as_node();
-        assert!(matches!(upcast_node, Node::ModuleNode { .. }));
+        shape
     }
+}
 
-    #[test]
-    fn constant_id_test() {
-        let source = "module Foo; x = 1; end";
-        let result = parse(source.as_ref());
+/// The options for printing a node as an s-expression with [`Node::to_sexp`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SexpOptions {
+    /// Whether to annotate each node with its `[start-end]` byte offsets.
+    pub locations: bool,
+}
 
-        let node = result.node();
-        let module = node.as_program_node().unwrap().statements().body().iter().next().unwrap();
-        let module = module.as_module_node().unwrap();
-        let locals = module.locals().iter().collect::<Vec<_>>();
+/// The kind of block passed to a call, as returned by
+/// [`CallNode::block_kind`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockKind {
+    /// No block is passed (`each`).
+    None,
 
-        assert_eq!(locals.len(), 1);
+    /// A literal `{ ... }` or `do ... end` block (`each { }`), which is a
+    /// [`BlockNode`].
+    Literal,
 
-        assert_eq!(locals[0].as_slice(), b"x");
+    /// A block argument (`each(&blk)` or the anonymous `each(&)`), which is a
+    /// [`BlockArgumentNode`].
+    Pass,
+}
+
+impl CallNode<'_> {
+    /// Classifies the arguments of the call (see [`ArgumentsNode::classify`]),
+    /// including whether a block argument is passed.
+    #[must_use]
+    pub fn classify_arguments(&self) -> ArgumentShape {
+        let mut shape = self.arguments().map(|arguments| arguments.classify()).unwrap_or_default();
+        shape.block |= self.block().is_some_and(|block| block.as_block_argument_node().is_some());
+        shape
     }
 
-    #[test]
-    fn optional_loc_test() {
-        let source = r#"
-module Example
-  x = call_func(3, 4)
-  y = x.call_func 5, 6
-end
-"#;
-        let result = parse(source.as_ref());
+    /// Returns what kind of block is passed to the call. The block itself is
+    /// returned by [`CallNode::block`], as either a [`BlockNode`] or a
+    /// [`BlockArgumentNode`].
+    #[must_use]
+    pub fn block_kind(&self) -> BlockKind {
+        match self.block() {
+            None => BlockKind::None,
+            Some(Node::BlockArgumentNode { .. }) => BlockKind::Pass,
+            Some(_) => BlockKind::Literal,
+        }
+    }
+}
 
-        let node = result.node();
-        let module = node.as_program_node().unwrap().statements().body().iter().next().unwrap();
-        let module = module.as_module_node().unwrap();
-        let body = module.body();
-        let writes = body.iter().next().unwrap().as_statements_node().unwrap().body().iter().collect::<Vec<_>>();
-        assert_eq!(writes.len(), 2);
+impl ParametersNode<'_> {
+    /// Returns the arity of the parameters. Argument forwarding (`...`)
+    /// accepts any arguments, so it counts as a rest, a keyword rest, and a
+    /// block parameter.
+    #[must_use]
+    pub fn arity(&self) -> Arity {
+        let forwarding = self.keyword_rest().is_some_and(|node| node.as_forwarding_parameter_node().is_some());
+        let (optional_keywords, required_keywords): (Vec<_>, Vec<_>) = self.keywords().iter().partition(|node| node.as_optional_keyword_parameter_node().is_some());
+
+        Arity {
+            required: self.requireds().iter().count() + self.posts().iter().count(),
+            optional: self.optionals().iter().count(),
+            rest: forwarding || self.rest().is_some_and(|node| node.as_rest_parameter_node().is_some()),
+            required_keywords: required_keywords.len(),
+            optional_keywords: optional_keywords.len(),
+            keyword_rest: forwarding || self.keyword_rest().is_some_and(|node| node.as_keyword_rest_parameter_node().is_some()),
+            block: forwarding || self.block().is_some(),
+        }
+    }
+}
 
-        let asgn = &writes[0];
-        let call = asgn.as_local_variable_write_node().unwrap().value();
-        let call = call.as_call_node().unwrap();
+impl BlockArgumentNode<'_> {
+    /// Returns whether this is the anonymous block forwarding form (`foo(&)`),
+    /// which passes along the block of the enclosing method. In that case
+    /// [`BlockArgumentNode::expression`] is `None`.
+    #[must_use]
+    pub fn is_anonymous(&self) -> bool {
+        self.expression().is_none()
+    }
+}
 
-        let call_operator_loc = call.call_operator_loc();
-        assert!(call_operator_loc.is_none());
-        let closing_loc = call.closing_loc();
-        assert!(closing_loc.is_some());
+impl CaseMatchNode<'_> {
+    /// Returns the number of `in` clauses, not counting the `else` clause.
+    #[must_use]
+    pub fn branch_count(&self) -> usize {
+        self.conditions().iter().count()
+    }
 
-        let asgn = &writes[1];
-        let call = asgn.as_local_variable_write_node().unwrap().value();
-        let call = call.as_call_node().unwrap();
+    /// Returns whether the pattern match has an `else` clause.
+    #[must_use]
+    pub fn has_else(&self) -> bool {
+        self.consequent().is_some()
+    }
+}
 
-        let call_operator_loc = call.call_operator_loc();
-        assert!(call_operator_loc.is_some());
-        let closing_loc = call.closing_loc();
-        assert!(closing_loc.is_none());
+impl<'pr> RescueNode<'pr> {
+    /// Returns the exception classes listed after `rescue`, e.g. `TypeError`
+    /// and `ArgumentError` in `rescue TypeError, ArgumentError => e`. This is
+    /// empty for a bare `rescue`, which rescues `StandardError`.
+    #[must_use]
+    pub fn exception_classes(&self) -> NodeList<'pr> {
+        self.exceptions()
     }
 
-    #[test]
-    fn frozen_strings_test() {
-        let source = r#"
-# frozen_string_literal: true
-"foo"
-"#;
-        let result = parse(source.as_ref());
-        assert!(result.frozen_string_literals());
+    /// Returns the target that the exception is bound to with `=>`, e.g. the
+    /// local variable target `e` in `rescue TypeError => e`.
+    #[must_use]
+    pub fn variable(&self) -> Option<Node<'pr>> {
+        self.reference()
+    }
+}
 
-        let source = "3";
-        let result = parse(source.as_ref());
-        assert!(!result.frozen_string_literals());
+/// Returns the bytes of a string owned by a node, which prism has already
+/// unescaped.
+fn string_bytes(string: &pm_string_t) -> &[u8] {
+    unsafe {
+        let length = pm_string_length(string);
+        if length == 0 {
+            return &[];
+        }
+        std::slice::from_raw_parts(pm_string_source(string), length)
     }
+}
 
-    #[test]
-    fn string_flags_test() {
-        let source = r#"
-# frozen_string_literal: true
-"foo"
-"#;
-        let result = parse(source.as_ref());
+/// Collects the names bound by a target of a multiple assignment or a
+/// destructuring block parameter, descending into nested targets and splats.
+fn collect_target_names<'pr>(node: &Node<'pr>, names: &mut Vec<ConstantId<'pr>>) {
+    match node {
+        Node::LocalVariableTargetNode { .. } => names.extend(node.as_local_variable_target_node().map(|node| node.name())),
+        Node::InstanceVariableTargetNode { .. } => names.extend(node.as_instance_variable_target_node().map(|node| node.name())),
+        Node::ClassVariableTargetNode { .. } => names.extend(node.as_class_variable_target_node().map(|node| node.name())),
+        Node::GlobalVariableTargetNode { .. } => names.extend(node.as_global_variable_target_node().map(|node| node.name())),
+        Node::ConstantTargetNode { .. } => names.extend(node.as_constant_target_node().map(|node| node.name())),
+        Node::RequiredParameterNode { .. } => names.extend(node.as_required_parameter_node().map(|node| node.name())),
+        Node::SplatNode { .. } => {
+            if let Some(expression) = node.as_splat_node().and_then(|node| node.expression()) {
+                collect_target_names(&expression, names);
+            }
+        },
+        Node::MultiTargetNode { .. } => {
+            if let Some(node) = node.as_multi_target_node() {
+                names.extend(node.flatten_names());
+            }
+        },
+        _ => {},
+    }
+}
 
-        let node = result.node();
-        let string = node.as_program_node().unwrap().statements().body().iter().next().unwrap();
-        let string = string.as_string_node().unwrap();
-        assert!(string.is_frozen());
+impl<'pr> MultiTargetNode<'pr> {
+    /// Returns every name bound by this target, in source order, including the
+    /// names inside of nested targets, e.g. `a`, `b`, and `c` for `(a, (b, c))`.
+    /// Targets without a name, like `foo.bar` or `foo[0]`, are skipped.
+    #[must_use]
+    pub fn flatten_names(&self) -> Vec<ConstantId<'pr>> {
+        let mut names = Vec::new();
 
-        let source = r#"
-"foo"
-"#;
-        let result = parse(source.as_ref());
+        for node in self.lefts().iter().chain(self.rest()).chain(self.rights().iter()) {
+            collect_target_names(&node, &mut names);
+        }
 
-        let node = result.node();
-        let string = node.as_program_node().unwrap().statements().body().iter().next().unwrap();
-        let string = string.as_string_node().unwrap();
-        assert!(!string.is_frozen());
+        names
     }
+}
 
-    #[test]
-    fn call_flags_test() {
-        let source = r#"
-x
-"#;
-        let result = parse(source.as_ref());
+impl<'pr> MultiWriteNode<'pr> {
+    /// Returns every name bound by the left-hand side of this assignment (see
+    /// [`MultiTargetNode::flatten_names`]).
+    #[must_use]
+    pub fn flatten_names(&self) -> Vec<ConstantId<'pr>> {
+        let mut names = Vec::new();
 
-        let node = result.node();
-        let call = node.as_program_node().unwrap().statements().body().iter().next().unwrap();
-        let call = call.as_call_node().unwrap();
-        assert!(call.is_variable_call());
+        for node in self.lefts().iter().chain(self.rest()).chain(self.rights().iter()) {
+            collect_target_names(&node, &mut names);
+        }
 
-        let source = r#"
-x&.foo
-"#;
-        let result = parse(source.as_ref());
+        names
+    }
+}
 
-        let node = result.node();
-        let call = node.as_program_node().unwrap().statements().body().iter().next().unwrap();
-        let call = call.as_call_node().unwrap();
-        assert!(call.is_safe_navigation());
+/// Returns whether the node unconditionally leaves the current block: a
+/// `return`, `break`, `next`, `redo`, or `retry`, or a receiverless call to
+/// `raise` or `fail`.
+fn is_jump(node: &Node<'_>) -> bool {
+    match node {
+        Node::ReturnNode { .. } | Node::BreakNode { .. } | Node::NextNode { .. } | Node::RedoNode { .. } | Node::RetryNode { .. } => true,
+        Node::CallNode { .. } => node.as_call_node().is_some_and(|call| call.receiver().is_none() && matches!(call.name().as_slice(), b"raise" | b"fail")),
+        _ => false,
     }
+}
 
-    #[test]
-    fn integer_flags_test() {
-        let source = r#"
-0b1
-"#;
-        let result = parse(source.as_ref());
+impl<'pr> StatementsNode<'pr> {
+    /// Returns the statements in order, collected into a `Vec` for indexing.
+    #[must_use]
+    pub fn nodes(&self) -> Vec<Node<'pr>> {
+        self.body().iter().collect()
+    }
 
-        let node = result.node();
-        let i = node.as_program_node().unwrap().statements().body().iter().next().unwrap();
-        let i = i.as_integer_node().unwrap();
-        assert!(i.is_binary());
-        assert!(!i.is_decimal());
-        assert!(!i.is_octal());
-        assert!(!i.is_hexadecimal());
+    /// Returns every pair of consecutive statements, e.g. `(a, b)` and `(b, c)`
+    /// for `a; b; c`, for lints that look at what directly follows a
+    /// statement.
+    pub fn pairs(&self) -> impl Iterator<Item = (Node<'pr>, Node<'pr>)> {
+        self.body().iter().zip(self.body().iter().skip(1))
+    }
 
-        let source = r#"
-1
-"#;
-        let result = parse(source.as_ref());
+    /// Returns the index of the first statement that can never run because an
+    /// earlier statement unconditionally leaves the block: a `return`,
+    /// `break`, `next`, `redo`, or `retry`, or a receiverless call to `raise`
+    /// or `fail`. Jumps inside of a nested conditional (including the modifier
+    /// forms like `return if x`) don't count. Returns `None` if every
+    /// statement is reachable.
+    #[must_use]
+    pub fn unreachable_tail(&self) -> Option<usize> {
+        let body = self.nodes();
+        body.iter().position(is_jump).map(|index| index + 1).filter(|&index| index < body.len())
+    }
+}
 
-        let node = result.node();
-        let i = node.as_program_node().unwrap().statements().body().iter().next().unwrap();
-        let i = i.as_integer_node().unwrap();
-        assert!(!i.is_binary());
-        assert!(i.is_decimal());
-        assert!(!i.is_octal());
-        assert!(!i.is_hexadecimal());
+impl<'pr> SourceFileNode<'pr> {
+    /// Returns the path that `__FILE__` evaluates to. [`parse`] doesn't take a
+    /// file path, so this is empty for the trees it returns.
+    #[must_use]
+    pub fn filepath_bytes(&self) -> &'pr [u8] {
+        string_bytes(unsafe { &(*self.as_node().as_ptr().cast::<pm_source_file_node_t>()).filepath })
+    }
+}
 
-        let source = r#"
-0o1
-"#;
+impl SourceLineNode<'_> {
+    /// Returns the line number that `__LINE__` evaluates to, counting from the
+    /// parser's start line.
+    #[must_use]
+    pub fn line(&self) -> usize {
+        let location = self.location();
+        line_column(location.parser, location.start_offset()).0
+    }
+}
+
+impl InterpolatedSymbolNode<'_> {
+    /// Returns whether the name of the symbol is known without running any
+    /// code, i.e. every part is literal text or an interpolation that is empty
+    /// or holds a single string literal, as in `:"foo#{}bar"` or
+    /// `:"foo#{"bar"}"`.
+    #[must_use]
+    pub fn is_all_static(&self) -> bool {
+        self.parts().iter().all(|part| match part {
+            Node::StringNode { .. } => true,
+            Node::EmbeddedStatementsNode { .. } => part.as_embedded_statements_node().and_then(|node| node.statements()).map_or(true, |statements| {
+                let mut body = statements.body().iter();
+                body.next().is_some_and(|node| node.as_string_node().is_some()) && body.next().is_none()
+            }),
+            _ => false,
+        })
+    }
+}
+
+impl AndNode<'_> {
+    /// Returns whether the operator is the `and` keyword rather than `&&`. The
+    /// two only differ in precedence, so both parse to an `AndNode`.
+    #[must_use]
+    pub fn is_keyword_form(&self) -> bool {
+        self.operator_loc().as_slice() == b"and"
+    }
+}
+
+impl OrNode<'_> {
+    /// Returns whether the operator is the `or` keyword rather than `||`. The
+    /// two only differ in precedence, so both parse to an `OrNode`.
+    #[must_use]
+    pub fn is_keyword_form(&self) -> bool {
+        self.operator_loc().as_slice() == b"or"
+    }
+}
+
+impl<'pr> Node<'pr> {
+    /// Returns whether there is a descending chain of nodes starting at this
+    /// node whose kinds match the given slash-separated path, e.g.
+    /// `"if_node/statements_node/call_node"`. Each segment is compared against
+    /// [`Node::kind_name`], and the `_node` suffix may be left off, so
+    /// `"if/statements/call"` is the same query. Each following segment must be
+    /// a direct child of the node matched by the previous one, and any branch
+    /// of the tree may satisfy the path.
+    #[must_use]
+    pub fn matches_path(&self, path: &str) -> bool {
+        fn matches(node: &Node<'_>, segments: &[&str]) -> bool {
+            let Some((segment, rest)) = segments.split_first() else { return true };
+            let kind = node.kind_name();
+
+            if kind != *segment && kind.strip_suffix("_node") != Some(segment) {
+                return false;
+            }
+
+            rest.is_empty() || node.child_nodes().iter().any(|child| matches(child, rest))
+        }
+
+        matches(self, &path.split('/').collect::<Vec<_>>())
+    }
+
+    /// Returns the lines that this node starts and ends on, as a
+    /// `(start_line, end_line)` pair. The lines are looked up in the parser's
+    /// newline table, so this doesn't need to rescan the source.
+    #[must_use]
+    pub fn line_span(&self) -> (usize, usize) {
+        let location = self.location();
+        let (start_line, _) = line_column(location.parser, location.start_offset());
+        let (end_line, _) = line_column(location.parser, location.end_offset());
+        (start_line, end_line)
+    }
+
+    /// Returns whether this node chooses what to evaluate based on a
+    /// condition: `if` (including the ternary and modifier forms), `unless`,
+    /// `while`, `until`, `&&`/`and`, and `||`/`or`.
+    #[must_use]
+    pub const fn is_conditional(&self) -> bool {
+        matches!(self, Self::IfNode { .. } | Self::UnlessNode { .. } | Self::WhileNode { .. } | Self::UntilNode { .. } | Self::AndNode { .. } | Self::OrNode { .. })
+    }
+
+    /// Returns the expression that governs a conditional node (see
+    /// [`Node::is_conditional`]): the predicate of an `if`, `unless`, `while`,
+    /// or `until`, or the left-hand side of `&&` and `||`, which decides
+    /// whether the right-hand side runs. Returns `None` for other nodes.
+    #[must_use]
+    pub fn condition(&self) -> Option<Node<'pr>> {
+        match self {
+            Self::IfNode { .. } => self.as_if_node().map(|node| node.predicate()),
+            Self::UnlessNode { .. } => self.as_unless_node().map(|node| node.predicate()),
+            Self::WhileNode { .. } => self.as_while_node().map(|node| node.predicate()),
+            Self::UntilNode { .. } => self.as_until_node().map(|node| node.predicate()),
+            Self::AndNode { .. } => self.as_and_node().map(|node| node.left()),
+            Self::OrNode { .. } => self.as_or_node().map(|node| node.left()),
+            _ => None,
+        }
+    }
+
+    /// Returns the locations of the opening and closing parentheses that were
+    /// written as part of this node: the parentheses of a [`ParenthesesNode`],
+    /// the optional parentheses around the arguments of a call, `super`, or
+    /// `yield`, around the operand of `defined?` or `^`, around the parameters
+    /// of a `def`, and around the targets of a multiple assignment. Returns
+    /// `None` if the node has no parentheses.
+    #[must_use]
+    pub fn paren_locations(&self) -> Option<(Location<'pr>, Location<'pr>)> {
+        match self {
+            Self::ParenthesesNode { .. } => self.as_parentheses_node().map(|node| (node.opening_loc(), node.closing_loc())),
+            Self::PinnedExpressionNode { .. } => self.as_pinned_expression_node().map(|node| (node.lparen_loc(), node.rparen_loc())),
+            // The opening of a call is `[` for index calls like `foo[0]`.
+            Self::CallNode { .. } => self.as_call_node().and_then(|node| node.opening_loc().zip(node.closing_loc())).filter(|(opening, _)| opening.as_slice() == b"("),
+            Self::SuperNode { .. } => self.as_super_node().and_then(|node| node.lparen_loc().zip(node.rparen_loc())),
+            Self::YieldNode { .. } => self.as_yield_node().and_then(|node| node.lparen_loc().zip(node.rparen_loc())),
+            Self::DefinedNode { .. } => self.as_defined_node().and_then(|node| node.lparen_loc().zip(node.rparen_loc())),
+            Self::DefNode { .. } => self.as_def_node().and_then(|node| node.lparen_loc().zip(node.rparen_loc())),
+            Self::MultiTargetNode { .. } => self.as_multi_target_node().and_then(|node| node.lparen_loc().zip(node.rparen_loc())),
+            Self::MultiWriteNode { .. } => self.as_multi_write_node().and_then(|node| node.lparen_loc().zip(node.rparen_loc())),
+            _ => None,
+        }
+    }
+
+    /// Returns whether this node was written with its own parentheses (see
+    /// [`Node::paren_locations`]). An explicitly parenthesized expression like
+    /// `(x)` is always a [`ParenthesesNode`].
+    #[must_use]
+    pub fn is_parenthesized(&self) -> bool {
+        self.paren_locations().is_some()
+    }
+
+    /// Returns the precedence of the operator this node was written with, or
+    /// `None` if it is not an operator. Higher levels bind tighter, following
+    /// the table in Ruby's operator precedence documentation, from `!`, `~`,
+    /// and unary `+` at 19 down to the modifier `if`, `unless`, `while`, and
+    /// `until` at 0. Operators called as methods (`a.+(b)`) are not operators,
+    /// and neither is a parenthesized `defined?(x)`.
+    #[must_use]
+    pub fn precedence(&self) -> Option<u8> {
+        match self {
+            Self::CallNode { .. } => {
+                let node = self.as_call_node()?;
+                if node.is_attribute_write() {
+                    return Some(4);
+                }
+                if node.receiver().is_none() || node.call_operator_loc().is_some() || node.opening_loc().is_some() {
+                    return None;
+                }
+
+                match node.name().as_slice() {
+                    b"!" if node.message_loc().is_some_and(|message| message.as_slice() == b"not") => Some(2),
+                    b"!" | b"~" | b"+@" => Some(19),
+                    b"**" => Some(18),
+                    b"-@" => Some(17),
+                    b"*" | b"/" | b"%" => Some(16),
+                    b"+" | b"-" => Some(15),
+                    b"<<" | b">>" => Some(14),
+                    b"&" => Some(13),
+                    b"|" | b"^" => Some(12),
+                    b">" | b">=" | b"<" | b"<=" => Some(11),
+                    b"<=>" | b"==" | b"===" | b"!=" | b"=~" | b"!~" => Some(10),
+                    _ => None,
+                }
+            },
+            Self::AndNode { .. } => self.as_and_node().map(|node| if node.is_keyword_form() { 1 } else { 9 }),
+            Self::OrNode { .. } => self.as_or_node().map(|node| if node.is_keyword_form() { 1 } else { 8 }),
+            Self::RangeNode { .. } | Self::FlipFlopNode { .. } => Some(7),
+            Self::IfNode { .. } => {
+                let node = self.as_if_node()?;
+                match node.if_keyword_loc() {
+                    None => Some(6),
+                    Some(keyword) if keyword.as_slice() == b"if" && node.end_keyword_loc().is_none() => Some(0),
+                    Some(_) => None,
+                }
+            },
+            Self::RescueModifierNode { .. } => Some(5),
+            Self::DefinedNode { .. } => self.as_defined_node().filter(|node| node.lparen_loc().is_none()).map(|_| 3),
+            Self::UnlessNode { .. } => self.as_unless_node().filter(|node| node.end_keyword_loc().is_none()).map(|_| 0),
+            Self::WhileNode { .. } => self.as_while_node().filter(|node| node.closing_loc().is_none()).map(|_| 0),
+            Self::UntilNode { .. } => self.as_until_node().filter(|node| node.closing_loc().is_none()).map(|_| 0),
+            _ if self.kind_name().ends_with("_write_node") => Some(4),
+            _ => None,
+        }
+    }
+
+    /// Returns whether this node is a call to a method with the given name, e.g.
+    /// `foo.bar(1)` is a call to `"bar"`. The name is compared against the
+    /// constant pool bytes, so this doesn't allocate.
+    #[must_use]
+    pub fn is_call_to(&self, name: &str) -> bool {
+        self.as_call_node().is_some_and(|node| node.name().as_slice() == name.as_bytes())
+    }
+
+    /// Returns whether this node is a call to a method whose name has the given
+    /// constant pool id, as returned by [`ParseResult::intern_names`]. This is
+    /// [`Node::is_call_to`] with an integer comparison in place of the bytes.
+    #[must_use]
+    pub fn is_call_to_id(&self, id: u32) -> bool {
+        self.as_call_node().is_some_and(|node| node.name().id() == id)
+    }
+
+    /// Returns the single expression inside of a redundant `begin` block, e.g.
+    /// `x` for `begin; x; end`, or this node itself otherwise. A `begin` block
+    /// is only redundant if it has no `rescue`, `else`, or `ensure` clause and
+    /// holds exactly one statement.
+    #[must_use]
+    pub fn unwrap_begin(&self) -> Self {
+        let inner = self
+            .as_begin_node()
+            .filter(|node| node.rescue_clause().is_none() && node.else_clause().is_none() && node.ensure_clause().is_none())
+            .and_then(|node| node.statements())
+            .and_then(|statements| {
+                let mut body = statements.body().iter();
+                body.next().filter(|_| body.next().is_none())
+            });
+
+        inner.unwrap_or(*self)
+    }
+
+    /// Returns whether this node is a string, an xstring (backticks), or a
+    /// symbol literal, with or without interpolation.
+    #[must_use]
+    pub const fn is_string_like(&self) -> bool {
+        matches!(
+            self,
+            Self::StringNode { .. } | Self::XStringNode { .. } | Self::SymbolNode { .. } | Self::InterpolatedStringNode { .. } | Self::InterpolatedXStringNode { .. } | Self::InterpolatedSymbolNode { .. }
+        )
+    }
+
+    /// Returns the unescaped content of a string, xstring, or symbol literal,
+    /// e.g. `a\nb` with a real newline for `"a\nb"`. Returns `None` for the
+    /// interpolated forms (even if they only join adjacent literals) and for
+    /// nodes that are not string-like.
+    #[must_use]
+    pub fn static_string_value(&self) -> Option<Vec<u8>> {
+        let string = unsafe {
+            match self {
+                Self::StringNode { .. } => &(*self.as_ptr().cast::<pm_string_node_t>()).unescaped,
+                Self::XStringNode { .. } => &(*self.as_ptr().cast::<pm_x_string_node_t>()).unescaped,
+                Self::SymbolNode { .. } => &(*self.as_ptr().cast::<pm_symbol_node_t>()).unescaped,
+                _ => return None,
+            }
+        };
+
+        Some(string_bytes(string).to_vec())
+    }
+
+    /// Calls the given function on this node and every node below it in
+    /// pre-order (see [`Node::child_nodes`]), together with how many levels
+    /// below this node they are, so this node is passed with a depth of 0. The
+    /// walk uses an explicit stack, so deep trees don't overflow the call
+    /// stack.
+    pub fn walk_with_depth(&self, mut f: impl FnMut(&Self, usize)) {
+        let mut stack = vec![(*self, 0)];
+
+        while let Some((node, depth)) = stack.pop() {
+            f(&node, depth);
+            stack.extend(node.child_nodes().into_iter().rev().map(|child| (child, depth + 1)));
+        }
+    }
+
+    /// Returns the shape of this node and its descendants as an s-expression,
+    /// e.g. `(program (statements (call (arguments (integer)))))` for
+    /// `foo(1)`. Each node is written as its [`Node::kind_name`] without the
+    /// `_node` suffix, followed by its children in field order. Fields that
+    /// aren't nodes are left out, so the output only changes when the shape of
+    /// the tree does.
+    #[must_use]
+    pub fn to_sexp(&self, options: SexpOptions) -> String {
+        fn write(node: &Node<'_>, options: SexpOptions, out: &mut String) {
+            let kind = node.kind_name();
+            out.push('(');
+            out.push_str(kind.strip_suffix("_node").unwrap_or(kind));
+
+            if options.locations {
+                let location = node.location();
+                out.push_str(" [");
+                out.push_str(&location.start_offset().to_string());
+                out.push('-');
+                out.push_str(&location.end_offset().to_string());
+                out.push(']');
+            }
+
+            for child in node.child_nodes() {
+                out.push(' ');
+                write(&child, options, out);
+            }
+
+            out.push(')');
+        }
+
+        let mut out = String::new();
+        write(self, options, &mut out);
+        out
+    }
+
+    /// Returns the s-expression of this node with every node annotated with
+    /// its `[start-end]` byte offsets, e.g. `(integer [4-5])`. This is
+    /// [`Node::to_sexp`] with [`SexpOptions::locations`] set, for debugging
+    /// location bugs.
+    #[must_use]
+    pub fn to_sexp_with_locations(&self) -> String {
+        self.to_sexp(SexpOptions { locations: true })
+    }
+
+    /// Returns every string literal at or below this node, in source order.
+    /// The literal parts of interpolated strings, symbols, xstrings, and
+    /// regular expressions (the `"a "` in `"a #{b}"`) are not literals on
+    /// their own, so they are skipped. Use [`Node::string_literals_in_calls`]
+    /// to only extract the arguments of certain calls.
+    #[must_use]
+    pub fn string_literals(&self) -> Vec<StringNode<'pr>> {
+        fn collect<'pr>(node: &Node<'pr>, literals: &mut Vec<StringNode<'pr>>) {
+            match node {
+                Node::StringNode { .. } => literals.extend(node.as_string_node()),
+                Node::InterpolatedStringNode { .. } | Node::InterpolatedSymbolNode { .. } | Node::InterpolatedXStringNode { .. } | Node::InterpolatedRegularExpressionNode { .. } | Node::InterpolatedMatchLastLineNode { .. } => {
+                    for child in node.child_nodes() {
+                        if child.as_string_node().is_none() {
+                            collect(&child, literals);
+                        }
+                    }
+                },
+                _ => {
+                    for child in node.child_nodes() {
+                        collect(&child, literals);
+                    }
+                },
+            }
+        }
+
+        let mut literals = Vec::new();
+        collect(self, &mut literals);
+        literals
+    }
+
+    /// Returns the string literals passed directly as arguments to the calls
+    /// at or below this node for which the predicate returns true, in source
+    /// order. For example, the strings to translate in an i18n extraction
+    /// pass are `node.string_literals_in_calls(|call| matches!(call.name().as_slice(), b"t" | b"translate"))`.
+    pub fn string_literals_in_calls(&self, predicate: impl Fn(&CallNode<'pr>) -> bool) -> Vec<StringNode<'pr>> {
+        let mut literals = Vec::new();
+
+        self.walk_with_depth(|node, _| {
+            let Some(call) = node.as_call_node().filter(|call| predicate(call)) else { return };
+            if let Some(arguments) = call.arguments() {
+                literals.extend(arguments.arguments().iter().filter_map(|argument| argument.as_string_node()));
+            }
+        });
+
+        literals
+    }
+
+    /// Returns whether this node starts before the other node in the source.
+    /// Both nodes must come from the same parse result.
+    #[must_use]
+    pub fn precedes(&self, other: &Node<'_>) -> bool {
+        let (location, other) = (self.location(), other.location());
+        debug_assert!(location.parser == other.parser, "nodes should come from the same parse result");
+        location.start_offset() < other.start_offset()
+    }
+
+    /// Returns whether this node starts after the other node in the source.
+    /// Both nodes must come from the same parse result.
+    #[must_use]
+    pub fn follows(&self, other: &Node<'_>) -> bool {
+        other.precedes(self)
+    }
+
+    /// Sorts the nodes into source order. Nodes that start at the same offset
+    /// are sorted outermost first, so a node comes before its descendants.
+    pub fn sort_by_position(nodes: &mut [Self]) {
+        nodes.sort_by_key(|node| {
+            let location = node.location();
+            (location.start_offset(), std::cmp::Reverse(location.end_offset()))
+        });
+    }
+
+    /// Returns the locals bound by this pattern, in source order, each with
+    /// the constant that the bound value is known to match, if any. For
+    /// example, `{user: User => u}` binds `u` with the guard `User`, and
+    /// `Point[x, y] => point` binds `x` and `y` without a guard and `point`
+    /// with the guard `Point`. Nested patterns are searched, and an `in`
+    /// clause or a `=>`/`in` match expression is searched through its pattern.
+    #[must_use]
+    pub fn pattern_bindings(&self) -> Vec<(ConstantId<'pr>, Option<Self>)> {
+        fn guard<'pr>(pattern: &Node<'pr>) -> Option<Node<'pr>> {
+            match pattern {
+                Node::ConstantReadNode { .. } | Node::ConstantPathNode { .. } => Some(*pattern),
+                Node::ArrayPatternNode { .. } => pattern.as_array_pattern_node().and_then(|node| node.constant()),
+                Node::FindPatternNode { .. } => pattern.as_find_pattern_node().and_then(|node| node.constant()),
+                Node::HashPatternNode { .. } => pattern.as_hash_pattern_node().and_then(|node| node.constant()),
+                _ => None,
+            }
+        }
+
+        fn collect<'pr>(node: &Node<'pr>, names: &mut Option<HashMap<&'pr [u8], ConstantId<'pr>>>, bindings: &mut Vec<(ConstantId<'pr>, Option<Node<'pr>>)>) {
+            match node {
+                Node::LocalVariableTargetNode { .. } => bindings.extend(node.as_local_variable_target_node().map(|node| (node.name(), None))),
+                Node::CapturePatternNode { .. } => {
+                    let Some(capture) = node.as_capture_pattern_node() else { return };
+                    let value = capture.value();
+                    collect(&value, names, bindings);
+                    if let Some(target) = capture.target().as_local_variable_target_node() {
+                        bindings.push((target.name(), guard(&value)));
+                    }
+                },
+                Node::AssocNode { .. } => {
+                    let Some(assoc) = node.as_assoc_node() else { return };
+                    if let Some(value) = assoc.value() {
+                        collect(&value, names, bindings);
+                        return;
+                    }
+
+                    // The shorthand `{user:}` binds the name of the key.
+                    let key = assoc.key();
+                    let Some(name) = key.as_symbol_node().and_then(|symbol| symbol.value_loc()) else { return };
+                    // The constant pool is only indexed once per call, for the
+                    // first shorthand key.
+                    let parser = key.location().parser;
+                    if let Some(&id) = names.get_or_insert_with(|| ConstantId::by_name(parser)).get(name.as_slice()) {
+                        bindings.push((id, None));
+                    }
+                },
+                // Pinned expressions and values are matched against, not bound.
+                Node::PinnedVariableNode { .. } | Node::PinnedExpressionNode { .. } => {},
+                _ => {
+                    for child in node.child_nodes() {
+                        collect(&child, names, bindings);
+                    }
+                },
+            }
+        }
+
+        let pattern = self
+            .as_in_node()
+            .map(|node| node.pattern())
+            .or_else(|| self.as_match_predicate_node().map(|node| node.pattern()))
+            .or_else(|| self.as_match_required_node().map(|node| node.pattern()));
+
+        let mut bindings = Vec::new();
+        collect(pattern.as_ref().unwrap_or(self), &mut None, &mut bindings);
+        bindings
+    }
+
+    /// Returns whether this node always evaluates to a truthy or a falsy value,
+    /// or `None` if it depends on the program. Only `false` and `nil` are
+    /// falsy in Ruby, so every other literal is truthy, including `0`, `""`,
+    /// and `[]`. Literals with interpolation or non-literal elements (`"#{x}"`
+    /// or `[x]`) are still constant in their truthiness, and a single
+    /// parenthesized expression has the truthiness of the expression.
+    #[must_use]
+    pub fn constant_truthiness(&self) -> Option<bool> {
+        match self {
+            Self::FalseNode { .. } | Self::NilNode { .. } => Some(false),
+            Self::TrueNode { .. }
+            | Self::IntegerNode { .. }
+            | Self::FloatNode { .. }
+            | Self::RationalNode { .. }
+            | Self::ImaginaryNode { .. }
+            | Self::StringNode { .. }
+            | Self::InterpolatedStringNode { .. }
+            | Self::SymbolNode { .. }
+            | Self::InterpolatedSymbolNode { .. }
+            | Self::RegularExpressionNode { .. }
+            | Self::InterpolatedRegularExpressionNode { .. }
+            | Self::ArrayNode { .. }
+            | Self::HashNode { .. }
+            | Self::RangeNode { .. }
+            | Self::LambdaNode { .. }
+            | Self::SourceFileNode { .. }
+            | Self::SourceLineNode { .. }
+            | Self::SourceEncodingNode { .. } => Some(true),
+            Self::ParenthesesNode { .. } => {
+                let statements = self.as_parentheses_node()?.body()?;
+                let mut body = statements.as_statements_node()?.body().iter();
+                body.next().filter(|_| body.next().is_none())?.constant_truthiness()
+            },
+            _ => None,
+        }
+    }
+
+    /// Returns whether this node is a guard clause: a modifier `if` or
+    /// `unless` whose statement leaves the current block, like
+    /// `return if x.nil?` or `raise ArgumentError unless valid?`. The jumps are
+    /// the same as for [`StatementsNode::unreachable_tail`].
+    #[must_use]
+    pub fn is_guard_clause(&self) -> bool {
+        let statements = match self {
+            Self::IfNode { .. } => self.as_if_node().filter(|node| node.if_keyword_loc().is_some_and(|keyword| keyword.as_slice() == b"if") && node.end_keyword_loc().is_none()).and_then(|node| node.statements()),
+            Self::UnlessNode { .. } => self.as_unless_node().filter(|node| node.end_keyword_loc().is_none()).and_then(|node| node.statements()),
+            _ => None,
+        };
+
+        statements.is_some_and(|statements| {
+            let mut body = statements.body().iter();
+            body.next().is_some_and(|node| is_jump(&node)) && body.next().is_none()
+        })
+    }
+
+    /// Returns whether this node is of one of the given kinds, e.g.
+    /// `node.kind_in(&[NodeType::IfNode, NodeType::UnlessNode])`.
+    #[must_use]
+    pub fn kind_in(&self, kinds: &[NodeType]) -> bool {
+        kinds.contains(&self.node_type())
+    }
+
+    /// Returns the name of the pseudo-variable that this node reads, if any:
+    /// one of the keywords `__FILE__`, `__LINE__`, and `__ENCODING__`, or a
+    /// receiverless call to `__dir__` or `__method__` without arguments.
+    #[must_use]
+    pub fn pseudo_variable(&self) -> Option<&'static str> {
+        match self {
+            Self::SourceFileNode { .. } => Some("__FILE__"),
+            Self::SourceLineNode { .. } => Some("__LINE__"),
+            Self::SourceEncodingNode { .. } => Some("__ENCODING__"),
+            Self::CallNode { .. } => {
+                let call = self.as_call_node()?;
+                if call.receiver().is_some() || call.arguments().is_some() || call.block().is_some() {
+                    return None;
+                }
+
+                match call.name().as_slice() {
+                    b"__dir__" => Some("__dir__"),
+                    b"__method__" => Some("__method__"),
+                    _ => None,
+                }
+            },
+            _ => None,
+        }
+    }
+
+    /// Returns whether this node is a symbol literal, with or without
+    /// interpolation, e.g. `:foo`, `:"foo"`, or `:"foo#{bar}"`.
+    #[must_use]
+    pub const fn is_symbol_like(&self) -> bool {
+        matches!(self, Self::SymbolNode { .. } | Self::InterpolatedSymbolNode { .. })
+    }
+
+    /// Returns the value of a `true` or `false` literal, or `None` for any
+    /// other node.
+    #[must_use]
+    pub const fn as_bool(&self) -> Option<bool> {
+        match self {
+            Self::TrueNode { .. } => Some(true),
+            Self::FalseNode { .. } => Some(false),
+            _ => None,
+        }
+    }
+
+    /// Returns whether this node is the `nil` literal.
+    #[must_use]
+    pub const fn is_nil(&self) -> bool {
+        matches!(self, Self::NilNode { .. })
+    }
+
+    /// Returns whether this node is `self`.
+    #[must_use]
+    pub const fn is_self(&self) -> bool {
+        matches!(self, Self::SelfNode { .. })
+    }
+
+    /// Returns the number of lines in this node that hold code, i.e. that
+    /// have a character that is neither whitespace nor part of one of the
+    /// given comments. This is the source lines of code (SLOC) metric, as
+    /// opposed to the raw number of lines. The comments are usually those of
+    /// [`ParseResult::comments`].
+    #[must_use]
+    pub fn sloc(&self, comments: &[Comment<'_>]) -> usize {
+        let location = self.location();
+        let start = location.start_offset();
+        let bytes = location.as_slice();
+
+        let mut commented = vec![false; bytes.len()];
+        for comment in comments {
+            let comment = comment.location();
+            let from = comment.start_offset().clamp(start, start + bytes.len()) - start;
+            let to = comment.end_offset().clamp(start, start + bytes.len()) - start;
+            commented[from..to].fill(true);
+        }
+
+        let mut offset = 0;
+        let mut count = 0;
+
+        for line in bytes.split(|&byte| byte == b'\n') {
+            if line.iter().enumerate().any(|(index, byte)| !byte.is_ascii_whitespace() && !commented[offset + index]) {
+                count += 1;
+            }
+            offset += line.len() + 1;
+        }
+
+        count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse;
+
+    #[test]
+    fn comments_test() {
+        let source = "# comment 1\n# comment 2\n# comment 3\n";
+        let result = parse(source.as_ref());
+
+        for comment in result.comments() {
+            let text = std::str::from_utf8(comment.text()).unwrap();
+            assert!(text.starts_with("# comment"));
+        }
+    }
+
+    #[test]
+    fn location_test() {
+        let source = "111 + 222 + 333";
+        let result = parse(source.as_ref());
+
+        let node = result.node();
+        let node = node.as_program_node().unwrap().statements().body().iter().next().unwrap();
+        let node = node.as_call_node().unwrap().receiver().unwrap();
+        let plus = node.as_call_node().unwrap();
+        let node = plus.arguments().unwrap().arguments().iter().next().unwrap();
+
+        let location = node.as_integer_node().unwrap().location();
+        let slice = std::str::from_utf8(result.as_slice(&location)).unwrap();
+
+        assert_eq!(slice, "222");
+        assert_eq!(6, location.start_offset());
+        assert_eq!(9, location.end_offset());
+
+        let recv_loc = plus.receiver().unwrap().location();
+        assert_eq!(recv_loc.as_slice(), b"111");
+        assert_eq!(0, recv_loc.start_offset());
+        assert_eq!(3, recv_loc.end_offset());
+
+        let joined = recv_loc.join(&location).unwrap();
+        assert_eq!(joined.as_slice(), b"111 + 222");
+
+        let not_joined = location.join(&recv_loc);
+        assert!(not_joined.is_none());
+
+        {
+            let result = parse(source.as_ref());
+            let node = result.node();
+            let node = node.as_program_node().unwrap().statements().body().iter().next().unwrap();
+            let node = node.as_call_node().unwrap().receiver().unwrap();
+            let plus = node.as_call_node().unwrap();
+            let node = plus.arguments().unwrap().arguments().iter().next().unwrap();
+
+            let location = node.as_integer_node().unwrap().location();
+            let not_joined = recv_loc.join(&location);
+            assert!(not_joined.is_none());
+
+            let not_joined = location.join(&recv_loc);
+            assert!(not_joined.is_none());
+        }
+
+        let location = node.location();
+        let slice = std::str::from_utf8(result.as_slice(&location)).unwrap();
+
+        assert_eq!(slice, "222");
+
+        let slice = std::str::from_utf8(location.as_slice()).unwrap();
+
+        assert_eq!(slice, "222");
+    }
+
+    #[test]
+    fn visitor_test() {
+        use super::{visit_interpolated_regular_expression_node, visit_regular_expression_node, InterpolatedRegularExpressionNode, RegularExpressionNode, Visit};
+
+        struct RegularExpressionVisitor {
+            count: usize,
+        }
+
+        impl Visit<'_> for RegularExpressionVisitor {
+            fn visit_interpolated_regular_expression_node(&mut self, node: &InterpolatedRegularExpressionNode<'_>) {
+                self.count += 1;
+                visit_interpolated_regular_expression_node(self, node);
+            }
+
+            fn visit_regular_expression_node(&mut self, node: &RegularExpressionNode<'_>) {
+                self.count += 1;
+                visit_regular_expression_node(self, node);
+            }
+        }
+
+        let source = "# comment 1\n# comment 2\nmodule Foo; class Bar; /abc #{/def/}/; end; end";
+        let result = parse(source.as_ref());
+
+        let mut visitor = RegularExpressionVisitor { count: 0 };
+        visitor.visit(&result.node());
+
+        assert_eq!(visitor.count, 2);
+    }
+
+    #[test]
+    fn node_upcast_test() {
+        use super::Node;
+
+        let source = "module Foo; end";
+        let result = parse(source.as_ref());
+
+        let node = result.node();
+        let upcast_node = node.as_program_node().unwrap().as_node();
+        assert!(matches!(upcast_node, Node::ProgramNode { .. }));
+
+        let node = node.as_program_node().unwrap().statements().body().iter().next().unwrap();
+        let upcast_node = node.as_module_node().unwrap().as_node();
+        assert!(matches!(upcast_node, Node::ModuleNode { .. }));
+    }
+
+    #[test]
+    fn constant_id_test() {
+        let source = "module Foo; x = 1; end";
+        let result = parse(source.as_ref());
+
+        let node = result.node();
+        let module = node.as_program_node().unwrap().statements().body().iter().next().unwrap();
+        let module = module.as_module_node().unwrap();
+        let locals = module.locals().iter().collect::<Vec<_>>();
+
+        assert_eq!(locals.len(), 1);
+
+        assert_eq!(locals[0].as_slice(), b"x");
+    }
+
+    #[test]
+    fn optional_loc_test() {
+        let source = r#"
+module Example
+  x = call_func(3, 4)
+  y = x.call_func 5, 6
+end
+"#;
+        let result = parse(source.as_ref());
+
+        let node = result.node();
+        let module = node.as_program_node().unwrap().statements().body().iter().next().unwrap();
+        let module = module.as_module_node().unwrap();
+        let body = module.body();
+        let writes = body.iter().next().unwrap().as_statements_node().unwrap().body().iter().collect::<Vec<_>>();
+        assert_eq!(writes.len(), 2);
+
+        let asgn = &writes[0];
+        let call = asgn.as_local_variable_write_node().unwrap().value();
+        let call = call.as_call_node().unwrap();
+
+        let call_operator_loc = call.call_operator_loc();
+        assert!(call_operator_loc.is_none());
+        let closing_loc = call.closing_loc();
+        assert!(closing_loc.is_some());
+
+        let asgn = &writes[1];
+        let call = asgn.as_local_variable_write_node().unwrap().value();
+        let call = call.as_call_node().unwrap();
+
+        let call_operator_loc = call.call_operator_loc();
+        assert!(call_operator_loc.is_some());
+        let closing_loc = call.closing_loc();
+        assert!(closing_loc.is_none());
+    }
+
+    #[test]
+    fn frozen_strings_test() {
+        let source = r#"
+# frozen_string_literal: true
+"foo"
+"#;
+        let result = parse(source.as_ref());
+        assert!(result.frozen_string_literals());
+
+        let source = "3";
+        let result = parse(source.as_ref());
+        assert!(!result.frozen_string_literals());
+    }
+
+    #[test]
+    fn string_flags_test() {
+        let source = r#"
+# frozen_string_literal: true
+"foo"
+"#;
+        let result = parse(source.as_ref());
+
+        let node = result.node();
+        let string = node.as_program_node().unwrap().statements().body().iter().next().unwrap();
+        let string = string.as_string_node().unwrap();
+        assert!(string.is_frozen());
+
+        let source = r#"
+"foo"
+"#;
+        let result = parse(source.as_ref());
+
+        let node = result.node();
+        let string = node.as_program_node().unwrap().statements().body().iter().next().unwrap();
+        let string = string.as_string_node().unwrap();
+        assert!(!string.is_frozen());
+    }
+
+    #[test]
+    fn call_flags_test() {
+        let source = r#"
+x
+"#;
+        let result = parse(source.as_ref());
+
+        let node = result.node();
+        let call = node.as_program_node().unwrap().statements().body().iter().next().unwrap();
+        let call = call.as_call_node().unwrap();
+        assert!(call.is_variable_call());
+
+        let source = r#"
+x&.foo
+"#;
+        let result = parse(source.as_ref());
+
+        let node = result.node();
+        let call = node.as_program_node().unwrap().statements().body().iter().next().unwrap();
+        let call = call.as_call_node().unwrap();
+        assert!(call.is_safe_navigation());
+    }
+
+    #[test]
+    fn integer_flags_test() {
+        let source = r#"
+0b1
+"#;
+        let result = parse(source.as_ref());
+
+        let node = result.node();
+        let i = node.as_program_node().unwrap().statements().body().iter().next().unwrap();
+        let i = i.as_integer_node().unwrap();
+        assert!(i.is_binary());
+        assert!(!i.is_decimal());
+        assert!(!i.is_octal());
+        assert!(!i.is_hexadecimal());
+
+        let source = r#"
+1
+"#;
+        let result = parse(source.as_ref());
+
+        let node = result.node();
+        let i = node.as_program_node().unwrap().statements().body().iter().next().unwrap();
+        let i = i.as_integer_node().unwrap();
+        assert!(!i.is_binary());
+        assert!(i.is_decimal());
+        assert!(!i.is_octal());
+        assert!(!i.is_hexadecimal());
+
+        let source = r#"
+0o1
+"#;
+        let result = parse(source.as_ref());
+
+        let node = result.node();
+        let i = node.as_program_node().unwrap().statements().body().iter().next().unwrap();
+        let i = i.as_integer_node().unwrap();
+        assert!(!i.is_binary());
+        assert!(!i.is_decimal());
+        assert!(i.is_octal());
+        assert!(!i.is_hexadecimal());
+
+        let source = r#"
+0x1
+"#;
+        let result = parse(source.as_ref());
+
+        let node = result.node();
+        let i = node.as_program_node().unwrap().statements().body().iter().next().unwrap();
+        let i = i.as_integer_node().unwrap();
+        assert!(!i.is_binary());
+        assert!(!i.is_decimal());
+        assert!(!i.is_octal());
+        assert!(i.is_hexadecimal());
+    }
+
+    #[test]
+    fn range_flags_test() {
+        let source = r#"
+0..1
+"#;
+        let result = parse(source.as_ref());
+
+        let node = result.node();
+        let range = node.as_program_node().unwrap().statements().body().iter().next().unwrap();
+        let range = range.as_range_node().unwrap();
+        assert!(!range.is_exclude_end());
+
+        let source = r#"
+0...1
+"#;
+        let result = parse(source.as_ref());
+
+        let node = result.node();
+        let range = node.as_program_node().unwrap().statements().body().iter().next().unwrap();
+        let range = range.as_range_node().unwrap();
+        assert!(range.is_exclude_end());
+    }
+
+    #[allow(clippy::too_many_lines, clippy::cognitive_complexity)]
+    #[test]
+    fn regex_flags_test() {
+        let source = r#"
+/a/i
+"#;
+        let result = parse(source.as_ref());
+
+        let node = result.node();
+        let regex = node.as_program_node().unwrap().statements().body().iter().next().unwrap();
+        let regex = regex.as_regular_expression_node().unwrap();
+        assert!(regex.is_ignore_case());
+        assert!(!regex.is_extended());
+        assert!(!regex.is_multi_line());
+        assert!(!regex.is_euc_jp());
+        assert!(!regex.is_ascii_8bit());
+        assert!(!regex.is_windows_31j());
+        assert!(!regex.is_utf_8());
+        assert!(!regex.is_once());
+
+        let source = r#"
+/a/x
+"#;
+        let result = parse(source.as_ref());
+
+        let node = result.node();
+        let regex = node.as_program_node().unwrap().statements().body().iter().next().unwrap();
+        let regex = regex.as_regular_expression_node().unwrap();
+        assert!(!regex.is_ignore_case());
+        assert!(regex.is_extended());
+        assert!(!regex.is_multi_line());
+        assert!(!regex.is_euc_jp());
+        assert!(!regex.is_ascii_8bit());
+        assert!(!regex.is_windows_31j());
+        assert!(!regex.is_utf_8());
+        assert!(!regex.is_once());
+
+        let source = r#"
+/a/m
+"#;
+        let result = parse(source.as_ref());
+
+        let node = result.node();
+        let regex = node.as_program_node().unwrap().statements().body().iter().next().unwrap();
+        let regex = regex.as_regular_expression_node().unwrap();
+        assert!(!regex.is_ignore_case());
+        assert!(!regex.is_extended());
+        assert!(regex.is_multi_line());
+        assert!(!regex.is_euc_jp());
+        assert!(!regex.is_ascii_8bit());
+        assert!(!regex.is_windows_31j());
+        assert!(!regex.is_utf_8());
+        assert!(!regex.is_once());
+
+        let source = r#"
+/a/e
+"#;
+        let result = parse(source.as_ref());
+
+        let node = result.node();
+        let regex = node.as_program_node().unwrap().statements().body().iter().next().unwrap();
+        let regex = regex.as_regular_expression_node().unwrap();
+        assert!(!regex.is_ignore_case());
+        assert!(!regex.is_extended());
+        assert!(!regex.is_multi_line());
+        assert!(regex.is_euc_jp());
+        assert!(!regex.is_ascii_8bit());
+        assert!(!regex.is_windows_31j());
+        assert!(!regex.is_utf_8());
+        assert!(!regex.is_once());
+
+        let source = r#"
+/a/n
+"#;
+        let result = parse(source.as_ref());
+
+        let node = result.node();
+        let regex = node.as_program_node().unwrap().statements().body().iter().next().unwrap();
+        let regex = regex.as_regular_expression_node().unwrap();
+        assert!(!regex.is_ignore_case());
+        assert!(!regex.is_extended());
+        assert!(!regex.is_multi_line());
+        assert!(!regex.is_euc_jp());
+        assert!(regex.is_ascii_8bit());
+        assert!(!regex.is_windows_31j());
+        assert!(!regex.is_utf_8());
+        assert!(!regex.is_once());
+
+        let source = r#"
+/a/s
+"#;
+        let result = parse(source.as_ref());
+
+        let node = result.node();
+        let regex = node.as_program_node().unwrap().statements().body().iter().next().unwrap();
+        let regex = regex.as_regular_expression_node().unwrap();
+        assert!(!regex.is_ignore_case());
+        assert!(!regex.is_extended());
+        assert!(!regex.is_multi_line());
+        assert!(!regex.is_euc_jp());
+        assert!(!regex.is_ascii_8bit());
+        assert!(regex.is_windows_31j());
+        assert!(!regex.is_utf_8());
+        assert!(!regex.is_once());
+
+        let source = r#"
+/a/u
+"#;
+        let result = parse(source.as_ref());
+
+        let node = result.node();
+        let regex = node.as_program_node().unwrap().statements().body().iter().next().unwrap();
+        let regex = regex.as_regular_expression_node().unwrap();
+        assert!(!regex.is_ignore_case());
+        assert!(!regex.is_extended());
+        assert!(!regex.is_multi_line());
+        assert!(!regex.is_euc_jp());
+        assert!(!regex.is_ascii_8bit());
+        assert!(!regex.is_windows_31j());
+        assert!(regex.is_utf_8());
+        assert!(!regex.is_once());
+
+        let source = r#"
+/a/o
+"#;
+        let result = parse(source.as_ref());
+
+        let node = result.node();
+        let regex = node.as_program_node().unwrap().statements().body().iter().next().unwrap();
+        let regex = regex.as_regular_expression_node().unwrap();
+        assert!(!regex.is_ignore_case());
+        assert!(!regex.is_extended());
+        assert!(!regex.is_multi_line());
+        assert!(!regex.is_euc_jp());
+        assert!(!regex.is_ascii_8bit());
+        assert!(!regex.is_windows_31j());
+        assert!(!regex.is_utf_8());
+        assert!(regex.is_once());
+    }
+
+    #[test]
+    fn visitor_traversal_test() {
+        use crate::{Node, Visit};
+
+        #[derive(Default)]
+        struct NodeCounts {
+            pre_parent: usize,
+            post_parent: usize,
+            pre_leaf: usize,
+            post_leaf: usize,
+        }
+
+        #[derive(Default)]
+        struct CountingVisitor {
+            counts: NodeCounts,
+        }
+
+        impl<'pr> Visit<'pr> for CountingVisitor {
+            fn visit_branch_node_enter(&mut self, _node: Node<'_>) {
+                self.counts.pre_parent += 1;
+            }
+
+            fn visit_branch_node_leave(&mut self) {
+                self.counts.post_parent += 1;
+            }
+
+            fn visit_leaf_node_enter(&mut self, _node: Node<'_>) {
+                self.counts.pre_leaf += 1;
+            }
+
+            fn visit_leaf_node_leave(&mut self) {
+                self.counts.post_leaf += 1;
+            }
+        }
+
+        let source = r#"
+module Example
+  x = call_func(3, 4)
+  y = x.call_func 5, 6
+end
+"#;
+        let result = parse(source.as_ref());
+        let node = result.node();
+        let mut visitor = CountingVisitor::default();
+        visitor.visit(&node);
+
+        assert_eq!(7, visitor.counts.pre_parent);
+        assert_eq!(7, visitor.counts.post_parent);
+        assert_eq!(6, visitor.counts.pre_leaf);
+        assert_eq!(6, visitor.counts.post_leaf);
+    }
+
+    #[test]
+    fn visitor_lifetime_test() {
+        use crate::{Node, Visit};
+
+        #[derive(Default)]
+        struct StackingNodeVisitor<'a> {
+            stack: Vec<Node<'a>>,
+            max_depth: usize,
+        }
+
+        impl<'pr> Visit<'pr> for StackingNodeVisitor<'pr> {
+            fn visit_branch_node_enter(&mut self, node: Node<'pr>) {
+                self.stack.push(node);
+            }
+
+            fn visit_branch_node_leave(&mut self) {
+                self.stack.pop();
+            }
+
+            fn visit_leaf_node_leave(&mut self) {
+                self.max_depth = self.max_depth.max(self.stack.len());
+            }
+        }
+
+        let source = r#"
+module Example
+  x = call_func(3, 4)
+  y = x.call_func 5, 6
+end
+"#;
+        let result = parse(source.as_ref());
+        let node = result.node();
+        let mut visitor = StackingNodeVisitor::default();
+        visitor.visit(&node);
+
+        assert_eq!(0, visitor.stack.len());
+        assert_eq!(5, visitor.max_depth);
+    }
+
+    #[test]
+    fn operator_write_test() {
+        use super::{OperatorWrite, OperatorWriteTarget};
+
+        let source = "@a += 1; @@b -= 2; $c *= 3; d = 0; d /= 4; E |= 5; F::G &= 6; h.i **= 7; j[0] += 8";
+        let result = parse(source.as_ref());
+
+        let node = result.node();
+        let statements = node.as_program_node().unwrap().statements().body().iter().collect::<Vec<_>>();
+        let writes = statements.iter().filter_map(super::Node::as_operator_write).collect::<Vec<_>>();
+        assert_eq!(writes.len(), 7);
+
+        let expected = [("@a", "+"), ("@@b", "-"), ("$c", "*"), ("d", "/"), ("E", "|"), ("F::G", "&"), ("i=", "**")];
+        for (write, (target, operator)) in writes.iter().zip(expected) {
+            let write: &dyn OperatorWrite<'_> = write.as_ref();
+            assert_eq!(write.operator(), operator);
+            assert!(write.value().as_integer_node().is_some());
+
+            match write.name_or_target() {
+                OperatorWriteTarget::Name(name) => assert_eq!(name.as_slice(), target.as_bytes()),
+                OperatorWriteTarget::Target(node) => assert_eq!(node.location().as_slice(), target.as_bytes()),
+            }
+        }
+
+        let constant = statements[5].as_constant_operator_write_node().unwrap();
+        assert_eq!(OperatorWrite::operator(&constant), "|");
+    }
+
+    #[test]
+    fn positions_test() {
+        let source = "foo\nbar baz\n\nqux";
+        let result = parse(source.as_ref());
+
+        let positions = result.positions(&[14, 0, 8, 12, 3, 4]);
+        assert_eq!(positions, vec![(4, 1), (1, 0), (2, 4), (3, 0), (1, 3), (2, 0)]);
+
+        assert!(result.positions(&[]).is_empty());
+    }
+
+    #[test]
+    fn case_branches_test() {
+        let source = "case x\nwhen 1 then :a\nwhen 2, 3 then :b\nelse :c\nend\ncase y\nwhen 1 then :a\nend";
+        let result = parse(source.as_ref());
+
+        let node = result.node();
+        let cases = node.as_program_node().unwrap().statements().body().iter().collect::<Vec<_>>();
+
+        let case = cases[0].as_case_node().unwrap();
+        assert_eq!(case.branch_count(), 2);
+        assert!(case.has_else());
+
+        let case = cases[1].as_case_node().unwrap();
+        assert_eq!(case.branch_count(), 1);
+        assert!(!case.has_else());
+
+        let source = "case x\nin Integer then :a\nin String then :b\nin [] then :c\nend\ncase y\nin _ then :a\nelse :b\nend";
+        let result = parse(source.as_ref());
+
+        let node = result.node();
+        let cases = node.as_program_node().unwrap().statements().body().iter().collect::<Vec<_>>();
+
+        let case = cases[0].as_case_match_node().unwrap();
+        assert_eq!(case.branch_count(), 3);
+        assert!(!case.has_else());
+
+        let case = cases[1].as_case_match_node().unwrap();
+        assert_eq!(case.branch_count(), 1);
+        assert!(case.has_else());
+    }
+
+    #[test]
+    fn line_span_test() {
+        let source = "# comment\ndef foo\n  bar\n  baz\nend\nqux";
+        let result = parse(source.as_ref());
+
+        let node = result.node();
+        let statements = node.as_program_node().unwrap().statements().body().iter().collect::<Vec<_>>();
+
+        assert_eq!(statements[0].line_span(), (2, 5));
+        assert_eq!(statements[1].line_span(), (6, 6));
+        assert_eq!(node.line_span(), (2, 6));
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn regex_pattern_test() {
+        let cases: &[(&str, Option<&str>, &str, &str)] = &[
+            (r"/\d+-\h{2}/", Some(r"[0-9]+-[0-9A-Fa-f]{2}"), "12-af", "x-zz"),
+            (r"/(?<year>\d{4})\/\w+/", Some(r"(?P<year>[0-9]{4})/[0-9A-Za-z_]+"), "2024/ok", "24/ok"),
+            ("/^foo.bar$/m", Some("^foo.bar$"), "x\nfoo\nbar", "foobar"),
+            ("/HELLO/i", Some("HELLO"), "hello", "help"),
+            (r"/a(?#comment)b{,2}/", Some("ab{0,2}"), "abb", "xb"),
+            (r"/[\s\D]\e/", Some(r"[\t\n\x0B\x0C\r\x20[^0-9]]\x1B"), "a\x1b", "1\x1b"),
+            (r"/é\u{41 42}\x41/", Some(r"é\x{41}\x{42}\x{41}"), "\u{e9}ABA", "e"),
+            ("/a [ ]b/x", Some(r"a [\x{20}]b"), "a b", "ab"),
+            (r"/a\ b/x", Some(r"a\x{20}b"), "a b", "ab"),
+            ("/a(?#café)b/", Some("ab"), "ab", "ax"),
+            ("/a # [note\nb/x", Some("a b"), "ab", "a"),
+            ("/a # (?=x)\nb/x", Some("a b"), "ab", "a"),
+            ("/(?<né>x)y/", Some("(?P<né>x)y"), "xy", "y"),
+            (r"/(a)\1/", None, "", ""),
+            (r"/foo(?=bar)/", None, "", ""),
+            (r"/(?>a+)b/", None, "", ""),
+            (r"/a++/", None, "", ""),
+            (r"/\Gfoo/", None, "", ""),
+        ];
+
+        for (source, expected, matching, non_matching) in cases {
+            let result = parse(source.as_ref());
+            let root = result.node();
+            let node = root.as_program_node().unwrap().statements().body().iter().next().unwrap();
+            let translated = node.as_regular_expression_node().unwrap().to_regex_pattern();
+
+            assert_eq!(translated.as_ref().map(|(pattern, _)| pattern.as_str()), *expected, "{source}");
+
+            if let Some((pattern, flags)) = translated {
+                let regex = flags.build(&pattern).unwrap();
+                assert!(regex.is_match(matching), "{source}");
+                assert!(!regex.is_match(non_matching), "{source}");
+            }
+        }
+    }
+
+    #[test]
+    fn cursor_ancestors_test() {
+        use super::Cursor;
+
+        let source = "class Foo\n  def bar\n    baz\n  end\nend";
+        let result = parse(source.as_ref());
+        let mut cursor = Cursor::new(&result.node());
+
+        while cursor.node().as_call_node().is_none() {
+            assert!(cursor.goto_first_child() || cursor.goto_next_sibling());
+        }
+
+        assert_eq!(cursor.ancestors().count(), cursor.depth());
+        assert!(cursor.ancestors().next().unwrap().as_statements_node().is_some());
+        assert!(cursor.ancestors().nth(1).unwrap().as_def_node().is_some());
+        assert!(cursor.ancestors().last().unwrap().as_program_node().is_some());
+
+        assert!(cursor.goto_parent());
+        assert!(cursor.node().as_statements_node().is_some());
+        assert!(!cursor.goto_next_sibling());
+        assert!(cursor.node().as_statements_node().is_some());
+    }
+
+    #[test]
+    fn block_argument_anonymous_test() {
+        let source = "def m(&); n(&); end\nm(&blk)";
+        let result = parse(source.as_ref());
+        let root = result.node();
+        let statements = root.as_program_node().unwrap().statements().body().iter().collect::<Vec<_>>();
+
+        let body = statements[0].as_def_node().unwrap().body().unwrap();
+        let call = body.as_statements_node().unwrap().body().iter().next().unwrap();
+        let block = call.as_call_node().unwrap().block().unwrap();
+        let anonymous = block.as_block_argument_node().unwrap();
+        assert!(anonymous.is_anonymous());
+        assert!(anonymous.expression().is_none());
+
+        let block = statements[1].as_call_node().unwrap().block().unwrap();
+        let named = block.as_block_argument_node().unwrap();
+        assert!(!named.is_anonymous());
+        assert_eq!(named.expression().unwrap().location().as_slice(), b"blk");
+    }
+
+    #[test]
+    fn detailed_location_test() {
+        use super::DetailedLocation;
+
+        let source = "foo\ndef bar\n  baz\nend";
+        let result = parse(source.as_ref());
+        let root = result.node();
+        let statements = root.as_program_node().unwrap().statements().body().iter().collect::<Vec<_>>();
+
+        let detailed = result.detailed_location(&statements[1].location());
+        assert_eq!(
+            detailed,
+            DetailedLocation {
+                start_offset: 4,
+                end_offset: 21,
+                start: (2, 0),
+                end: (4, 3)
+            }
+        );
+
+        let detailed = result.detailed_location(&statements[0].location());
+        assert_eq!(
+            detailed,
+            DetailedLocation {
+                start_offset: 0,
+                end_offset: 3,
+                start: (1, 0),
+                end: (1, 3)
+            }
+        );
+    }
+
+    #[test]
+    fn class_variable_names_test() {
+        let source = "class Foo\n  @@count = 0\n  @@count += 1\n  @@count ||= 2\n  @@count &&= 3\n  @@count\nend";
+        let result = parse(source.as_ref());
+        let root = result.node();
+        let class = root.as_program_node().unwrap().statements().body().iter().next().unwrap();
+        let body = class.as_class_node().unwrap().body().unwrap();
+        let statements = body.as_statements_node().unwrap().body().iter().collect::<Vec<_>>();
+
+        let write = statements[0].as_class_variable_write_node().unwrap();
+        assert_eq!(write.name_str(), "@@count");
+        assert_eq!(write.bare_name_str(), "count");
+
+        let operator_write = statements[1].as_class_variable_operator_write_node().unwrap();
+        assert_eq!(operator_write.name_str(), "@@count");
+        assert_eq!(operator_write.bare_name_str(), "count");
+
+        assert_eq!(statements[2].as_class_variable_or_write_node().unwrap().bare_name_str(), "count");
+        assert_eq!(statements[3].as_class_variable_and_write_node().unwrap().bare_name_str(), "count");
+
+        let read = statements[4].as_class_variable_read_node().unwrap();
+        assert_eq!(read.name_str(), "@@count");
+        assert_eq!(read.bare_name_str(), "count");
+    }
+
+    #[test]
+    fn matches_path_test() {
+        let source = "if foo\n  bar(1)\nend";
+        let result = parse(source.as_ref());
+        let root = result.node();
+        let node = root.as_program_node().unwrap().statements().body().iter().next().unwrap();
+
+        assert_eq!(node.kind_name(), "if_node");
+        assert!(node.matches_path("if_node/statements_node/call_node"));
+        assert!(node.matches_path("if/statements/call/arguments/integer"));
+        assert!(node.matches_path("if_node/call_node"));
+        assert!(!node.matches_path("if_node/statements_node/integer_node"));
+        assert!(!node.matches_path("unless_node/statements_node/call_node"));
+    }
+
+    #[test]
+    fn parse_expression_test() {
+        use super::parse_expression;
+
+        let result = parse_expression(b"foo(1) + 2");
+        assert_eq!(result.errors().count(), 0);
+
+        let result = parse_expression(b"foo\nbar; baz");
+        let errors = result.errors().collect::<Vec<_>>();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].message(), "unexpected trailing content after the expression");
+        assert_eq!(errors[0].location().as_slice(), b"bar; baz");
+        assert_eq!(parse(b"foo\nbar; baz").errors().count(), 0);
+
+        let result = parse_expression(b"");
+        let errors = result.errors().collect::<Vec<_>>();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].message(), "expected an expression");
+        assert_eq!((errors[0].location().start_offset(), errors[0].location().end_offset()), (0, 0));
+
+        assert_eq!(parse_expression(b"1;\n  ").errors().count(), 0);
+
+        let result = parse_expression(b"def foo");
+        let errors = result.errors().collect::<Vec<_>>();
+        assert_eq!(errors.len(), parse(b"def foo").errors().count());
+        assert!(errors.iter().all(|error| error.message() != "unexpected trailing content after the expression"));
+    }
+
+    #[test]
+    fn condition_test() {
+        let source = "if a then 1 end\nb ? 2 : 3\n4 unless c\nwhile d do end\n5 until e\nf && 6\ng or 7\n8";
+        let result = parse(source.as_ref());
+        let root = result.node();
+        let statements = root.as_program_node().unwrap().statements().body().iter().collect::<Vec<_>>();
+
+        for (statement, condition) in statements.iter().zip(["a", "b", "c", "d", "e", "f", "g"]) {
+            assert!(statement.is_conditional());
+            assert_eq!(statement.condition().unwrap().location().as_slice(), condition.as_bytes());
+        }
+
+        assert!(!statements[7].is_conditional());
+        assert!(statements[7].condition().is_none());
+    }
+
+    #[test]
+    fn structure_debug_test() {
+        let expected = "ProgramNode\n  locals: [\"x\"]\n  statements:\n    StatementsNode\n      body:\n        LocalVariableWriteNode\n          name: \"x\"\n          depth: 0\n          value:\n            CallNode\n              flags: [variable_call, ignore_visibility]\n              receiver: nil\n              name: \"foo\"\n              arguments: nil\n              block: nil\n";
+
+        let result = parse(b"x = foo");
+        assert_eq!(result.node().structure_debug(), expected);
+
+        let result = parse(b"\n\n  x   =\n    foo");
+        assert_eq!(result.node().structure_debug(), expected);
+    }
+
+    #[test]
+    fn constant_references_test() {
+        let source = "module Foo\n  class Bar < Base\n    Baz::Qux\n  end\nend\nclass Foo::Bar\n  ::Top\nend\nmodule Foo\n  X\n  x(W)::Y\nend\nZ\nV = 1\nFoo::Y = 2\nFoo::Y += 1\nA::B, c = 1, 2";
+        let result = parse(source.as_ref());
+
+        let references = result.constant_references().into_iter().map(|reference| (reference.scope.join(" > "), reference.path)).collect::<Vec<_>>();
+        let expected = [("Foo", "Base"), ("Foo > Bar", "Baz::Qux"), ("", "Foo"), ("Foo::Bar", "::Top"), ("Foo", "X"), ("Foo", "W"), ("", "Z"), ("", "Foo"), ("", "Foo"), ("", "A")];
+
+        assert_eq!(references, expected.map(|(scope, path)| (scope.to_string(), path.to_string())));
+    }
+
+    #[test]
+    fn flag_is_set_test() {
+        let result = parse(b"foo&.bar; foo.bar; 1");
+        let root = result.node();
+        let statements = root.as_program_node().unwrap().statements().body().iter().collect::<Vec<_>>();
+
+        assert_eq!(statements[0].flag_is_set("safe_navigation"), Some(true));
+        assert_eq!(statements[0].flag_is_set("SAFE_NAVIGATION"), Some(true));
+        assert_eq!(statements[1].flag_is_set("safe_navigation"), Some(false));
+        assert_eq!(statements[1].flag_is_set("frozen"), None);
+        assert_eq!(statements[2].flag_is_set("decimal"), Some(true));
+        assert_eq!(statements[2].flag_is_set("safe_navigation"), None);
+    }
+
+    #[test]
+    fn def_endless_test() {
+        let source = "def square(x) = x * x\ndef self.zero = 0\ndef cube(x)\n  x * x * x\nend\ndef empty; end";
+        let result = parse(source.as_ref());
+        let root = result.node();
+        let endless = root.as_program_node().unwrap().statements().body().iter().map(|node| node.as_def_node().unwrap().is_endless()).collect::<Vec<_>>();
+
+        assert_eq!(endless, vec![true, true, false, false]);
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn to_json_value_test() {
+        let result = parse(b"foo&.bar(1)");
+        let json = result.node().to_json_value();
+
+        assert_eq!(json["type"], "ProgramNode");
+        assert_eq!(json["location"]["end_offset"], 11);
+
+        let call = &json["statements"]["body"][0];
+        assert_eq!(call["type"], "CallNode");
+        assert_eq!(call["name"], "bar");
+        assert_eq!(call["flags"], serde_json::json!(["safe_navigation"]));
+        assert_eq!(call["receiver"]["name"], "foo");
+        assert_eq!(call["arguments"]["arguments"][0]["type"], "IntegerNode");
+        assert_eq!(call["block"], serde_json::Value::Null);
+        assert_eq!(call["message_loc"], serde_json::json!({ "start_offset": 5, "end_offset": 8 }));
+
+        let result = parse(b"\"a\\tb\"");
+        let json = result.node().to_json_value();
+        assert_eq!(json["statements"]["body"][0]["unescaped"], "a\tb");
+    }
+
+    #[test]
+    fn argument_shape_test() {
+        use super::ArgumentShape;
+
+        let result = parse(b"foo(a, *b, c, d: 1, e: 2, **f, &g)\nbar(...)\nbaz { }");
+        let root = result.node();
+        let calls = root.as_program_node().unwrap().statements().body().iter().map(|node| node.as_call_node().unwrap()).collect::<Vec<_>>();
+
+        let expected = ArgumentShape {
+            positional: vec![0, 2],
+            splats: vec![1],
+            keyword_hash: Some(3),
+            keywords: 2,
+            double_splats: 1,
+            forwarding: None,
+            block: true,
+        };
+        assert_eq!(calls[0].classify_arguments(), expected);
+        assert_eq!(calls[0].arguments().unwrap().classify(), ArgumentShape { block: false, ..expected });
+
+        assert_eq!(calls[1].classify_arguments(), ArgumentShape { forwarding: Some(0), ..ArgumentShape::default() });
+        assert_eq!(calls[2].classify_arguments(), ArgumentShape::default());
+    }
+
+    #[test]
+    fn enclosing_test() {
+        use super::Node;
+
+        let source = "module Foo\n  def bar\n    baz(1)\n  end\nend\nqux";
+        let result = parse(source.as_ref());
+        let root = result.node();
+        let statements = root.as_program_node().unwrap().statements().body().iter().collect::<Vec<_>>();
+
+        let module = statements[0].as_module_node().unwrap();
+        let def = module.body().unwrap().as_statements_node().unwrap().body().iter().next().unwrap();
+        let call = def.as_def_node().unwrap().body().unwrap().as_statements_node().unwrap().body().iter().next().unwrap();
+        let argument = call.as_call_node().unwrap().arguments().unwrap().arguments().iter().next().unwrap();
+
+        let ancestors = result.ancestors_of(&argument);
+        assert_eq!(
+            ancestors.iter().map(Node::kind_name).collect::<Vec<_>>(),
+            ["arguments_node", "call_node", "statements_node", "def_node", "statements_node", "module_node", "statements_node", "program_node"]
+        );
+
+        assert_eq!(result.enclosing_method(&argument).unwrap().name().as_slice(), b"bar");
+        assert_eq!(result.enclosing_namespace(&argument).unwrap().as_module_node().unwrap().name().as_slice(), b"Foo");
+        assert_eq!(result.enclosing_namespace(&def).unwrap().kind_name(), "module_node");
+
+        assert!(result.enclosing_method(&statements[1]).is_none());
+        assert!(result.enclosing_namespace(&statements[1]).is_none());
+        assert!(result.ancestors_of(&root).is_empty());
+    }
+
+    #[test]
+    fn loop_begin_modifier_test() {
+        let result = parse(b"begin; x; end while y\nx while y\nbegin; x; end until y\nx until y");
+        let root = result.node();
+        let statements = root.as_program_node().unwrap().statements().body().iter().collect::<Vec<_>>();
+
+        assert!(statements[0].as_while_node().unwrap().is_begin_modifier());
+        assert!(!statements[1].as_while_node().unwrap().is_begin_modifier());
+        assert!(statements[2].as_until_node().unwrap().is_begin_modifier());
+        assert!(!statements[3].as_until_node().unwrap().is_begin_modifier());
+    }
+
+    #[test]
+    fn leading_keyword_loc_test() {
+        let source = "def foo; end\nclass Foo; end\nmodule Bar; end\nif a then b end\nwhile a do end\nx unless y\nreturn 1\na ? b : c\nfoo(1)";
         let result = parse(source.as_ref());
+        let root = result.node();
+
+        let keywords = root.as_program_node().unwrap().statements().body().iter().map(|node| node.leading_keyword_loc().map(|location| location.as_slice())).collect::<Vec<_>>();
+        let expected: [Option<&[u8]>; 9] = [Some(b"def"), Some(b"class"), Some(b"module"), Some(b"if"), Some(b"while"), Some(b"unless"), Some(b"return"), None, None];
+
+        assert_eq!(keywords, expected);
+
+        let result = parse(b"case x\nin 1 then y\nelse z\nend\na ? b : c");
+        let statements = result.node().as_program_node().unwrap().statements().body().iter().collect::<Vec<_>>();
+
+        let case = statements[0].as_case_match_node().unwrap();
+        let clause = case.conditions().iter().next().unwrap();
+        assert_eq!(clause.leading_keyword_loc().map(|location| location.as_slice()), Some(b"in".as_slice()));
+        assert_eq!(case.consequent().unwrap().as_node().leading_keyword_loc().map(|location| location.as_slice()), Some(b"else".as_slice()));
+
+        let ternary = statements[1].as_if_node().unwrap().consequent().unwrap();
+        assert!(ternary.leading_keyword_loc().is_none());
+    }
+
+    #[cfg(feature = "symbols")]
+    #[test]
+    fn symbol_table_test() {
+        use super::symbols::{SymbolKind, SymbolTable};
+
+        let source =
+            "module Foo\n  VERSION = 1\n\n  class Bar < Base\n    def baz(a, b = 1, *c, d:, **e, &f)\n      g = a\n      g = b\n      [1].each { |h| i = h }\n    end\n\n    class << self\n      def create; end\n    end\n\n    def self.build; end\n  end\nend\n\nx = Foo::Bar\nFoo::LIMIT = 2\n";
+        let result = parse(source.as_ref());
+        let table = SymbolTable::build(&result.node());
+
+        let symbols = table.symbols().iter().map(|symbol| (table.qualified_name(symbol), symbol.kind)).collect::<Vec<_>>();
+        let expected = [
+            ("Foo", SymbolKind::Module),
+            ("Foo::VERSION", SymbolKind::Constant),
+            ("Foo::Bar", SymbolKind::Class),
+            ("Foo::Bar#baz", SymbolKind::Method),
+            ("a", SymbolKind::Local),
+            ("b", SymbolKind::Local),
+            ("c", SymbolKind::Local),
+            ("d", SymbolKind::Local),
+            ("e", SymbolKind::Local),
+            ("f", SymbolKind::Local),
+            ("g", SymbolKind::Local),
+            ("h", SymbolKind::Local),
+            ("i", SymbolKind::Local),
+            ("Foo::Bar.create", SymbolKind::SingletonMethod),
+            ("Foo::Bar.build", SymbolKind::SingletonMethod),
+            ("x", SymbolKind::Local),
+            ("Foo::LIMIT", SymbolKind::Constant),
+        ];
+        assert_eq!(symbols, expected.map(|(name, kind)| (name.to_string(), kind)));
+
+        let baz = table.lookup("baz").next().unwrap();
+        assert_eq!(table.parent(baz).unwrap().name, "Bar");
+        assert_eq!(table.lookup("g").count(), 1);
+        assert_eq!(table.children(3).count(), 9);
+
+        let offset = source.find("i = h").unwrap();
+        assert_eq!(table.symbol_at(offset).unwrap().name, "i");
+        assert_eq!(table.scope_at(offset).unwrap().name, "baz");
+        assert_eq!(table.scope_at(source.find("VERSION").unwrap()).unwrap().name, "Foo");
+        assert!(table.scope_at(source.find("x =").unwrap()).is_none());
+    }
+
+    #[test]
+    fn keyword_form_test() {
+        let result = parse(b"a and b\na && b\na or b\na || b");
+        let root = result.node();
+        let statements = root.as_program_node().unwrap().statements().body();
+        let forms = statements.iter().map(|node| node.as_and_node().map_or_else(|| node.as_or_node().unwrap().is_keyword_form(), |node| node.is_keyword_form())).collect::<Vec<_>>();
+
+        assert_eq!(forms, vec![true, false, true, false]);
+    }
+
+    #[test]
+    fn parenthesized_test() {
+        let result = parse(b"(x)\nx\nfoo(1)\nfoo 1\nfoo[1]");
+        let root = result.node();
+        let statements = root.as_program_node().unwrap().statements().body();
+        let parenthesized = statements.iter().map(|node| node.is_parenthesized()).collect::<Vec<_>>();
+
+        assert_eq!(parenthesized, vec![true, false, true, false, false]);
+
+        let (opening, closing) = statements.iter().next().unwrap().paren_locations().unwrap();
+        assert_eq!((opening.start_offset(), closing.start_offset()), (0, 2));
+    }
+
+    #[test]
+    fn char_len_test() {
+        let source = "\"caf\u{e9} \u{1f600}\"";
+        let result = parse(source.as_ref());
+        let root = result.node();
+        let string = root.as_program_node().unwrap().statements().body().iter().next().unwrap();
+        let content = string.as_string_node().unwrap().content_loc();
+
+        assert_eq!(content.as_slice().len(), 10);
+        assert_eq!(content.char_len(), 6);
+    }
+
+    #[test]
+    fn file_header_test() {
+        let source = "#!/usr/bin/env ruby\n# frozen_string_literal: true\n\nputs 'hi'\n";
+        let result = parse(source.as_ref());
+
+        assert_eq!(result.shebang().unwrap().as_slice(), b"#!/usr/bin/env ruby");
+        assert_eq!(result.frozen_string_literal(), Some(true));
+        assert_eq!(result.encoding_name(), "UTF-8");
+
+        let result = parse(b"# encoding: ascii-8bit\n# frozen-string-literal: false\nputs 'hi'\n# frozen_string_literal: true\n");
+        assert!(result.shebang().is_none());
+        assert_eq!(result.frozen_string_literal(), Some(false));
+        assert_eq!(result.encoding_name(), "ASCII-8BIT");
+
+        assert_eq!(parse(b"puts 'hi'").frozen_string_literal(), None);
+    }
+
+    #[test]
+    fn precedence_test() {
+        let source = "a + b\na * b\na ** b\n-a\n!a\nnot a\na == b\na && b\na and b\na..b\na ? b : c\na rescue b\nx = 1\nx += 1\nfoo.bar = 1\ndefined? a\na if b\nif a then b end\nfoo\na.+(b)";
+        let result = parse(source.as_ref());
+        let root = result.node();
+        let statements = root.as_program_node().unwrap().statements().body();
+        let levels = statements.iter().map(|node| node.precedence()).collect::<Vec<_>>();
+
+        assert_eq!(
+            levels,
+            vec![Some(15), Some(16), Some(18), Some(17), Some(19), Some(2), Some(10), Some(9), Some(1), Some(7), Some(6), Some(5), Some(4), Some(4), Some(4), Some(3), Some(0), None, None, None]
+        );
+    }
+
+    #[test]
+    fn intern_names_test() {
+        let result = parse(b"foo.bar(baz)\nfoo.qux");
+        let names = result.intern_names(&["bar", "qux", "missing"]);
+
+        assert_eq!(names["missing"], None);
+
+        let root = result.node();
+        let calls = root.as_program_node().unwrap().statements().body().iter().map(|node| node.as_call_node().unwrap().name().id()).collect::<Vec<_>>();
+        assert_eq!(calls, vec![names["bar"].unwrap(), names["qux"].unwrap()]);
+    }
+
+    #[test]
+    fn is_call_to_test() {
+        let result = parse(b"foo.bar(1)");
+        let root = result.node();
+        let call = root.as_program_node().unwrap().statements().body().iter().next().unwrap();
+
+        assert!(call.is_call_to("bar"));
+        assert!(!call.is_call_to("foo"));
+        assert!(!root.is_call_to("bar"));
+
+        let names = result.intern_names(&["bar", "foo"]);
+        assert!(call.is_call_to_id(names["bar"].unwrap()));
+        assert!(!call.is_call_to_id(names["foo"].unwrap()));
+    }
+
+    #[test]
+    fn rescue_exception_classes_test() {
+        let source = "begin\n  foo\nrescue TypeError, ArgumentError => e\n  bar\nrescue\n  baz\nend";
+        let result = parse(source.as_ref());
+        let root = result.node();
+        let begin = root.as_program_node().unwrap().statements().body().iter().next().unwrap();
+        let rescue = begin.as_begin_node().unwrap().rescue_clause().unwrap();
+
+        let classes = rescue.exception_classes().iter().map(|node| node.location().as_slice()).collect::<Vec<_>>();
+        assert_eq!(classes, vec![b"TypeError".as_slice(), b"ArgumentError"]);
+        assert_eq!(rescue.variable().unwrap().location().as_slice(), b"e");
+
+        let bare = rescue.consequent().unwrap();
+        assert_eq!(bare.exception_classes().iter().count(), 0);
+        assert!(bare.variable().is_none());
+    }
+
+    #[test]
+    fn unwrap_begin_test() {
+        let result = parse(b"begin; x; end\nbegin; x; y; end\nbegin; x; rescue; end\nz");
+        let root = result.node();
+        let unwrapped = root.as_program_node().unwrap().statements().body().iter().map(|node| node.unwrap_begin().kind_name()).collect::<Vec<_>>();
+
+        assert_eq!(unwrapped, vec!["call_node", "begin_node", "begin_node", "call_node"]);
+    }
+
+    #[test]
+    fn flatten_names_test() {
+        let result = parse(b"(a, (b, *c)), @d, foo.bar = 1, 2, 3");
+        let root = result.node();
+        let write = root.as_program_node().unwrap().statements().body().iter().next().unwrap();
+        let write = write.as_multi_write_node().unwrap();
+
+        let names = write.flatten_names().into_iter().map(|name| name.as_slice()).collect::<Vec<_>>();
+        assert_eq!(names, vec![b"a".as_slice(), b"b", b"c", b"@d"]);
+
+        let nested = write.lefts().iter().next().unwrap();
+        let names = nested.as_multi_target_node().unwrap().flatten_names().into_iter().map(|name| name.as_slice()).collect::<Vec<_>>();
+        assert_eq!(names, vec![b"a".as_slice(), b"b", b"c"]);
+    }
+
+    #[test]
+    fn static_string_value_test() {
+        let result = parse(b"\"a\\tb\"\n:sym\n%s(foo)\n`ls`\n\"a#{b}c\"\n42");
+        let root = result.node();
+        let statements = root.as_program_node().unwrap().statements().body();
+
+        let string_like = statements.iter().map(|node| node.is_string_like()).collect::<Vec<_>>();
+        assert_eq!(string_like, vec![true, true, true, true, true, false]);
+
+        let values = statements.iter().map(|node| node.static_string_value()).collect::<Vec<_>>();
+        assert_eq!(values, vec![Some(b"a\tb".to_vec()), Some(b"sym".to_vec()), Some(b"foo".to_vec()), Some(b"ls".to_vec()), None, None]);
+    }
+
+    #[test]
+    fn method_visibilities_test() {
+        use super::Visibility;
+
+        let source = "class Foo\n  def a; end\n  def b; end\n  private :b\n\n  private\n\n  def c; end\n  def self.d; end\n  public def e; end\n\n  protected\n\n  def f; end\nend\n\ndef g; end";
+        let result = parse(source.as_ref());
+        let visibilities = result.method_visibilities();
+
+        let find = |name: &str| {
+            let start = source.find(&format!("def {name}")).or_else(|| source.find(&format!("def self.{name}"))).unwrap();
+            visibilities.iter().find(|((offset, _), _)| *offset == start).map(|(_, visibility)| *visibility)
+        };
+
+        assert_eq!(find("a"), Some(Visibility::Public));
+        assert_eq!(find("b"), Some(Visibility::Private));
+        assert_eq!(find("c"), Some(Visibility::Private));
+        assert_eq!(find("d"), Some(Visibility::Public));
+        assert_eq!(find("e"), Some(Visibility::Public));
+        assert_eq!(find("f"), Some(Visibility::Protected));
+        assert_eq!(find("g"), None);
+    }
+
+    #[test]
+    fn is_adjacent_to_test() {
+        let result = parse(b"foo.bar baz");
+        let root = result.node();
+        let call = root.as_program_node().unwrap().statements().body().iter().next().unwrap();
+        let call = call.as_call_node().unwrap();
+
+        let receiver = call.receiver().unwrap().location();
+        let operator = call.call_operator_loc().unwrap();
+        let message = call.message_loc().unwrap();
+        let arguments = call.arguments().unwrap().location();
+
+        assert!(receiver.is_adjacent_to(&operator));
+        assert!(message.is_adjacent_to(&operator));
+        assert!(!message.is_adjacent_to(&arguments));
+        assert!(!receiver.is_adjacent_to(&message));
+    }
+
+    #[test]
+    fn walk_with_depth_test() {
+        let result = parse(b"foo(1) if bar");
+        let root = result.node();
+        let mut visited = Vec::new();
+        root.walk_with_depth(|node, depth| visited.push((node.kind_name(), depth)));
+
+        assert_eq!(visited, vec![("program_node", 0), ("statements_node", 1), ("if_node", 2), ("call_node", 3), ("statements_node", 3), ("call_node", 4), ("arguments_node", 5), ("integer_node", 6)]);
+    }
+
+    #[test]
+    fn fingerprint_test() {
+        let result = parse(b"foo(1)");
+        let expected = "ProgramNode 0..6 locals=[]\n  statements: StatementsNode 0..6\n    body[0]: CallNode 0..6 flags=[ignore_visibility] call_operator_loc=nil name=\"foo\" message_loc=0..3 opening_loc=3..4 closing_loc=5..6\n      arguments: ArgumentsNode 4..5 flags=[]\n        arguments[0]: IntegerNode 4..5 flags=[decimal]\n";
+        assert_eq!(result.fingerprint(), expected);
+
+        let result = parse(b"x = \"a\\\"\xC3\xA9\"");
+        assert!(result.fingerprint().contains("StringNode 4..11 flags=[] opening_loc=4..5 content_loc=5..10 closing_loc=10..11 unescaped=\"a\\\"\\xC3\\xA9\""));
+    }
+
+    #[test]
+    fn unreachable_tail_test() {
+        let tails = ["def m; return; x; end", "def m; x; raise 'no'; y; z; end", "def m; return if a; x; end", "def m; x; return; end"]
+            .iter()
+            .map(|source| {
+                let result = parse(source.as_ref());
+                let root = result.node();
+                let def = root.as_program_node().unwrap().statements().body().iter().next().unwrap();
+                let body = def.as_def_node().unwrap().body().unwrap();
+                body.as_statements_node().unwrap().unreachable_tail()
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(tails, vec![Some(1), Some(2), None, None]);
+    }
+
+    #[test]
+    fn block_kind_test() {
+        use super::BlockKind;
+
+        let result = parse(b"each { }\neach(&blk)\neach\neach do end");
+        let root = result.node();
+        let kinds = root.as_program_node().unwrap().statements().body().iter().map(|node| node.as_call_node().unwrap().block_kind()).collect::<Vec<_>>();
+
+        assert_eq!(kinds, vec![BlockKind::Literal, BlockKind::Pass, BlockKind::None, BlockKind::Literal]);
+    }
+
+    #[test]
+    fn sexp_test() {
+        use super::SexpOptions;
+
+        let result = parse(b"foo(1)");
+        let root = result.node();
+
+        assert_eq!(root.to_sexp(SexpOptions::default()), "(program (statements (call (arguments (integer)))))");
+        assert_eq!(root.to_sexp_with_locations(), "(program [0-6] (statements [0-6] (call [0-6] (arguments [4-5] (integer [4-5])))))");
+    }
+
+    #[test]
+    fn arity_test() {
+        use super::Arity;
+
+        let result = parse(b"def a(x, y = 1, *rest, z, k:, o: 2, **opts, &blk); end\ndef b; end\ndef c(...); end\ndef d(x, **nil); end");
+        let root = result.node();
+        let arities = root.as_program_node().unwrap().statements().body().iter().map(|node| node.as_def_node().unwrap().arity()).collect::<Vec<_>>();
+
+        assert_eq!(
+            arities[0],
+            Arity {
+                required: 2,
+                optional: 1,
+                rest: true,
+                required_keywords: 1,
+                optional_keywords: 1,
+                keyword_rest: true,
+                block: true,
+            }
+        );
+        assert_eq!(arities[1], Arity::default());
+        assert_eq!(
+            arities[2],
+            Arity {
+                rest: true,
+                keyword_rest: true,
+                block: true,
+                ..Arity::default()
+            }
+        );
+        assert_eq!(arities[3], Arity { required: 1, ..Arity::default() });
+    }
+
+    #[test]
+    fn string_literals_test() {
+        let result = parse(b"t('hello')\nputs \"a #{b} c\", 'd'\ntranslate(\"e\", x)");
+        let root = result.node();
+
+        let literals = root.string_literals().iter().map(|node| node.content_loc().as_slice()).collect::<Vec<_>>();
+        assert_eq!(literals, vec![b"hello".as_slice(), b"d", b"e"]);
+
+        let translated = root.string_literals_in_calls(|call| matches!(call.name().as_slice(), b"t" | b"translate")).iter().map(|node| node.content_loc().as_slice()).collect::<Vec<_>>();
+        assert_eq!(translated, vec![b"hello".as_slice(), b"e"]);
+    }
+
+    #[test]
+    fn sort_by_position_test() {
+        use super::Node;
+
+        let result = parse(b"foo.bar(baz)");
+        let root = result.node();
+        let call = root.as_program_node().unwrap().statements().body().iter().next().unwrap();
+        let call_node = call.as_call_node().unwrap();
+        let receiver = call_node.receiver().unwrap();
+        let argument = call_node.arguments().unwrap().as_node();
+
+        assert!(receiver.precedes(&argument));
+        assert!(argument.follows(&receiver));
+        assert!(!call.precedes(&receiver));
+
+        let mut nodes = vec![argument, receiver, call];
+        Node::sort_by_position(&mut nodes);
+        assert_eq!(nodes.iter().map(|node| node.location().as_slice()).collect::<Vec<_>>(), vec![b"foo.bar(baz)".as_slice(), b"foo", b"baz"]);
+    }
+
+    #[test]
+    fn pattern_bindings_test() {
+        let result = parse(b"case x\nin {user: User => u, id:}\nin Point[a, *rest] => point\nin [^y, Foo::Bar => z]\nend");
+        let root = result.node();
+        let case = root.as_program_node().unwrap().statements().body().iter().next().unwrap();
+        let bindings = case
+            .as_case_match_node()
+            .unwrap()
+            .conditions()
+            .iter()
+            .map(|condition| condition.pattern_bindings().into_iter().map(|(name, guard)| (name.as_slice(), guard.map(|guard| guard.location().as_slice()))).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+
+        assert_eq!(bindings[0], vec![(b"u".as_slice(), Some(b"User".as_slice())), (b"id", None)]);
+        assert_eq!(bindings[1], vec![(b"a".as_slice(), None), (b"rest", None), (b"point", Some(b"Point".as_slice()))]);
+        assert_eq!(bindings[2], vec![(b"z".as_slice(), Some(b"Foo::Bar".as_slice()))]);
+    }
+
+    #[test]
+    fn requires_test() {
+        use super::RequireKind;
+
+        let source = "require \"json\"\nrequire_relative \"./foo\"\nrequire \"#{dir}/bar\"\nrequire path\nmodule A\n  autoload :B, \"a/b\"\nend\nfoo.require \"baz\"";
+        let result = parse(source.as_ref());
+        let requires = result.requires().into_iter().map(|info| (info.kind, info.path, info.top_level)).collect::<Vec<_>>();
+
+        assert_eq!(requires, vec![(RequireKind::Require, "json".to_string(), true), (RequireKind::RequireRelative, "./foo".to_string(), true), (RequireKind::Autoload, "a/b".to_string(), false)]);
+    }
+
+    #[test]
+    fn events_test() {
+        use super::{Event, Scalar};
+
+        let result = parse(b"foo(1)");
+        let root = result.node();
+        let call = root.as_program_node().unwrap().statements().body().iter().next().unwrap();
+        let arguments = call.as_call_node().unwrap().arguments().unwrap().as_node();
+
+        let events = arguments
+            .events()
+            .map(|event| match event {
+                Event::Enter(name) => format!("enter {name}"),
+                Event::Field(name) => format!("field {name}"),
+                Event::Scalar(Scalar::List(count)) => format!("list {count}"),
+                Event::Scalar(Scalar::Flags(flags)) => format!("flags {flags:?}"),
+                Event::Scalar(scalar) => format!("{scalar:?}"),
+                Event::Leave(name) => format!("leave {name}"),
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            events,
+            vec!["enter ArgumentsNode", "field flags", "flags []", "field arguments", "list 1", "enter IntegerNode", "field flags", "flags [\"decimal\"]", "leave IntegerNode", "leave ArgumentsNode"]
+        );
+
+        let depth = root.events().fold((0, 0), |(depth, max), event| match event {
+            Event::Enter(_) => (depth + 1, max.max(depth + 1)),
+            Event::Leave(_) => (depth - 1, max),
+            _ => (depth, max),
+        });
+        assert_eq!(depth, (0, 5));
+    }
+
+    #[test]
+    fn constant_truthiness_test() {
+        let result = parse(b"true\nfalse\nnil\n0\n1.5\n\"\"\n:sym\n[]\n{}\n1..2\n/x/\n(nil)\nfoo\n@bar");
+        let root = result.node();
+        let truthiness = root.as_program_node().unwrap().statements().body().iter().map(|node| node.constant_truthiness()).collect::<Vec<_>>();
+
+        assert_eq!(truthiness, vec![Some(true), Some(false), Some(false), Some(true), Some(true), Some(true), Some(true), Some(true), Some(true), Some(true), Some(true), Some(false), None, None]);
+    }
+
+    #[test]
+    fn node_dispatch_test() {
+        use super::{NodeDispatch, NodeType};
 
-        let node = result.node();
-        let i = node.as_program_node().unwrap().statements().body().iter().next().unwrap();
-        let i = i.as_integer_node().unwrap();
-        assert!(!i.is_binary());
-        assert!(!i.is_decimal());
-        assert!(i.is_octal());
-        assert!(!i.is_hexadecimal());
+        let dispatch = NodeDispatch::builder(|_| -1).on(NodeType::IntegerNode, |node| std::str::from_utf8(node.location().as_slice()).unwrap().parse().unwrap()).on(NodeType::NilNode, |_| 0).build();
 
-        let source = r#"
-0x1
-"#;
-        let result = parse(source.as_ref());
+        let result = parse(b"42\nnil\nfoo");
+        let root = result.node();
+        let values = root.as_program_node().unwrap().statements().body().iter().map(|node| dispatch.dispatch(&node)).collect::<Vec<_>>();
 
-        let node = result.node();
-        let i = node.as_program_node().unwrap().statements().body().iter().next().unwrap();
-        let i = i.as_integer_node().unwrap();
-        assert!(!i.is_binary());
-        assert!(!i.is_decimal());
-        assert!(!i.is_octal());
-        assert!(i.is_hexadecimal());
+        assert_eq!(values, vec![42, 0, -1]);
     }
 
     #[test]
-    fn range_flags_test() {
-        let source = r#"
-0..1
-"#;
+    fn yield_count_test() {
+        let source = "def each\n  yield 1\n  [2, 3].each { |x| yield x }\n  def inner\n    yield\n  end\n  yield(yield)\nend\ndef none; end";
         let result = parse(source.as_ref());
+        let root = result.node();
+        let counts = root.as_program_node().unwrap().statements().body().iter().map(|node| node.as_def_node().unwrap().yield_count()).collect::<Vec<_>>();
 
-        let node = result.node();
-        let range = node.as_program_node().unwrap().statements().body().iter().next().unwrap();
-        let range = range.as_range_node().unwrap();
-        assert!(!range.is_exclude_end());
+        assert_eq!(counts, vec![4, 0]);
+    }
 
-        let source = r#"
-0...1
-"#;
-        let result = parse(source.as_ref());
+    #[test]
+    fn statement_pairs_test() {
+        let result = parse(b"a = 1\nputs a\nb");
+        let root = result.node();
+        let statements = root.as_program_node().unwrap().statements();
 
-        let node = result.node();
-        let range = node.as_program_node().unwrap().statements().body().iter().next().unwrap();
-        let range = range.as_range_node().unwrap();
-        assert!(range.is_exclude_end());
+        assert_eq!(statements.nodes().len(), 3);
+
+        let pairs = statements.pairs().map(|(first, second)| (first.kind_name(), second.location().as_slice())).collect::<Vec<_>>();
+        assert_eq!(pairs, vec![("local_variable_write_node", b"puts a".as_slice()), ("call_node", b"b".as_slice())]);
     }
 
-    #[allow(clippy::too_many_lines, clippy::cognitive_complexity)]
     #[test]
-    fn regex_flags_test() {
-        let source = r#"
-/a/i
-"#;
-        let result = parse(source.as_ref());
+    fn guard_clause_test() {
+        let result = parse(b"return if x.nil?\nraise ArgumentError unless valid?\nnext if a\nfoo if bar\nif x then return end\nx ? (return) : y");
+        let root = result.node();
+        let guards = root.as_program_node().unwrap().statements().body().iter().map(|node| node.is_guard_clause()).collect::<Vec<_>>();
 
-        let node = result.node();
-        let regex = node.as_program_node().unwrap().statements().body().iter().next().unwrap();
-        let regex = regex.as_regular_expression_node().unwrap();
-        assert!(regex.is_ignore_case());
-        assert!(!regex.is_extended());
-        assert!(!regex.is_multi_line());
-        assert!(!regex.is_euc_jp());
-        assert!(!regex.is_ascii_8bit());
-        assert!(!regex.is_windows_31j());
-        assert!(!regex.is_utf_8());
-        assert!(!regex.is_once());
+        assert_eq!(guards, vec![true, true, true, false, false, false]);
+    }
 
-        let source = r#"
-/a/x
-"#;
-        let result = parse(source.as_ref());
+    #[test]
+    fn merge_locations_test() {
+        use super::merge_locations;
+
+        let result = parse(b"foo(bar, baz) + qux");
+        let root = result.node();
+        let plus = root.as_program_node().unwrap().statements().body().iter().next().unwrap();
+        let plus = plus.as_call_node().unwrap();
+        let call = plus.receiver().unwrap();
+        let call = call.as_call_node().unwrap();
+        let arguments = call.arguments().unwrap().arguments().iter().collect::<Vec<_>>();
+        let qux = plus.arguments().unwrap().arguments().iter().next().unwrap();
 
-        let node = result.node();
-        let regex = node.as_program_node().unwrap().statements().body().iter().next().unwrap();
-        let regex = regex.as_regular_expression_node().unwrap();
-        assert!(!regex.is_ignore_case());
-        assert!(regex.is_extended());
-        assert!(!regex.is_multi_line());
-        assert!(!regex.is_euc_jp());
-        assert!(!regex.is_ascii_8bit());
-        assert!(!regex.is_windows_31j());
-        assert!(!regex.is_utf_8());
-        assert!(!regex.is_once());
+        let offsets = |locations: Vec<crate::Location<'_>>| locations.iter().map(|location| (location.start_offset(), location.end_offset())).collect::<Vec<_>>();
 
-        let source = r#"
-/a/m
-"#;
-        let result = parse(source.as_ref());
+        // Overlapping: the message and the whole call collapse into the call.
+        assert_eq!(offsets(merge_locations(&[call.message_loc().unwrap(), call.location(), arguments[0].location()])), vec![(0, 13)]);
 
-        let node = result.node();
-        let regex = node.as_program_node().unwrap().statements().body().iter().next().unwrap();
-        let regex = regex.as_regular_expression_node().unwrap();
-        assert!(!regex.is_ignore_case());
-        assert!(!regex.is_extended());
-        assert!(regex.is_multi_line());
-        assert!(!regex.is_euc_jp());
-        assert!(!regex.is_ascii_8bit());
-        assert!(!regex.is_windows_31j());
-        assert!(!regex.is_utf_8());
-        assert!(!regex.is_once());
+        // Adjacent: `foo` and `(` touch, so they merge.
+        assert_eq!(offsets(merge_locations(&[call.opening_loc().unwrap(), call.message_loc().unwrap()])), vec![(0, 4)]);
 
-        let source = r#"
-/a/e
-"#;
-        let result = parse(source.as_ref());
+        // Disjoint: the arguments are kept apart and sorted.
+        assert_eq!(offsets(merge_locations(&[qux.location(), arguments[1].location(), arguments[0].location()])), vec![(4, 7), (9, 12), (16, 19)]);
 
-        let node = result.node();
-        let regex = node.as_program_node().unwrap().statements().body().iter().next().unwrap();
-        let regex = regex.as_regular_expression_node().unwrap();
-        assert!(!regex.is_ignore_case());
-        assert!(!regex.is_extended());
-        assert!(!regex.is_multi_line());
-        assert!(regex.is_euc_jp());
-        assert!(!regex.is_ascii_8bit());
-        assert!(!regex.is_windows_31j());
-        assert!(!regex.is_utf_8());
-        assert!(!regex.is_once());
+        assert!(merge_locations(&[]).is_empty());
+    }
 
-        let source = r#"
-/a/n
-"#;
+    #[test]
+    fn doc_comment_test() {
+        let source = "# Not documentation.\n\nclass Foo\n  # Adds two numbers.\n  #\n  #   add(1, 2) # => 3\n  def add(a, b) = a + b\n\n=begin\nSubtracts two numbers.\n=end\n  def sub(a, b) = a - b\n\n  x = 1 # trailing\n  def mul(a, b) = a * b\n\n  def div(a, b) = a / b\nend\n";
         let result = parse(source.as_ref());
+        let root = result.node();
+        let class = root.as_program_node().unwrap().statements().body().iter().next().unwrap();
+        let body = class.as_class_node().unwrap().body().unwrap();
+        let defs = body.as_statements_node().unwrap().body().iter().filter(|node| node.as_def_node().is_some()).collect::<Vec<_>>();
+
+        assert_eq!(result.doc_comment(&class), None);
+        assert_eq!(result.doc_comment(&defs[0]).as_deref(), Some("Adds two numbers.\n\n  add(1, 2) # => 3"));
+        assert_eq!(result.doc_comment(&defs[1]).as_deref(), Some("Subtracts two numbers."));
+        assert_eq!(result.doc_comment(&defs[2]), None);
+        assert_eq!(result.doc_comment(&defs[3]), None);
+    }
 
-        let node = result.node();
-        let regex = node.as_program_node().unwrap().statements().body().iter().next().unwrap();
-        let regex = regex.as_regular_expression_node().unwrap();
-        assert!(!regex.is_ignore_case());
-        assert!(!regex.is_extended());
-        assert!(!regex.is_multi_line());
-        assert!(!regex.is_euc_jp());
-        assert!(regex.is_ascii_8bit());
-        assert!(!regex.is_windows_31j());
-        assert!(!regex.is_utf_8());
-        assert!(!regex.is_once());
+    #[test]
+    fn kind_in_test() {
+        use super::NodeType;
 
-        let source = r#"
-/a/s
-"#;
-        let result = parse(source.as_ref());
+        let result = parse(b"foo if bar\nbaz unless qux\nwhile x; end");
+        let root = result.node();
+        let conditionals = root.as_program_node().unwrap().statements().body().iter().map(|node| node.kind_in(&[NodeType::IfNode, NodeType::UnlessNode])).collect::<Vec<_>>();
 
-        let node = result.node();
-        let regex = node.as_program_node().unwrap().statements().body().iter().next().unwrap();
-        let regex = regex.as_regular_expression_node().unwrap();
-        assert!(!regex.is_ignore_case());
-        assert!(!regex.is_extended());
-        assert!(!regex.is_multi_line());
-        assert!(!regex.is_euc_jp());
-        assert!(!regex.is_ascii_8bit());
-        assert!(regex.is_windows_31j());
-        assert!(!regex.is_utf_8());
-        assert!(!regex.is_once());
+        assert_eq!(conditionals, vec![true, true, false]);
+        assert_eq!(root.node_type(), NodeType::ProgramNode);
+        assert!(!root.kind_in(&[]));
+    }
 
-        let source = r#"
-/a/u
-"#;
-        let result = parse(source.as_ref());
+    #[test]
+    fn pseudo_variable_test() {
+        let result = parse(b"puts __FILE__\n\nlog(__LINE__, __method__, __dir__, __ENCODING__, foo.__dir__)");
+        let root = result.node();
+        let statements = root.as_program_node().unwrap().statements().body().iter().collect::<Vec<_>>();
+
+        let file = statements[0].as_call_node().unwrap().arguments().unwrap().arguments().iter().next().unwrap();
+        assert_eq!(file.pseudo_variable(), Some("__FILE__"));
+        assert_eq!(file.as_source_file_node().unwrap().filepath_bytes(), b"");
+
+        let arguments = statements[1].as_call_node().unwrap().arguments().unwrap().arguments().iter().collect::<Vec<_>>();
+        let names = arguments.iter().map(super::Node::pseudo_variable).collect::<Vec<_>>();
+        assert_eq!(names, vec![Some("__LINE__"), Some("__method__"), Some("__dir__"), Some("__ENCODING__"), None]);
+        assert_eq!(arguments[0].as_source_line_node().unwrap().line(), 3);
+        assert_eq!(statements[0].pseudo_variable(), None);
+    }
 
-        let node = result.node();
-        let regex = node.as_program_node().unwrap().statements().body().iter().next().unwrap();
-        let regex = regex.as_regular_expression_node().unwrap();
-        assert!(!regex.is_ignore_case());
-        assert!(!regex.is_extended());
-        assert!(!regex.is_multi_line());
-        assert!(!regex.is_euc_jp());
-        assert!(!regex.is_ascii_8bit());
-        assert!(!regex.is_windows_31j());
-        assert!(regex.is_utf_8());
-        assert!(!regex.is_once());
+    #[test]
+    fn node_copy_test() {
+        let result = parse(b"foo");
+        let root = result.node();
+        let copy = root;
 
-        let source = r#"
-/a/o
-"#;
-        let result = parse(source.as_ref());
+        assert_eq!(copy.as_ptr(), root.as_ptr());
+        assert_eq!(copy.kind_name(), root.kind_name());
+    }
 
-        let node = result.node();
-        let regex = node.as_program_node().unwrap().statements().body().iter().next().unwrap();
-        let regex = regex.as_regular_expression_node().unwrap();
-        assert!(!regex.is_ignore_case());
-        assert!(!regex.is_extended());
-        assert!(!regex.is_multi_line());
-        assert!(!regex.is_euc_jp());
-        assert!(!regex.is_ascii_8bit());
-        assert!(!regex.is_windows_31j());
-        assert!(!regex.is_utf_8());
-        assert!(regex.is_once());
+    #[test]
+    fn top_level_statements_test() {
+        let library = parse(b"require \"set\"\n\nVERSION = \"1.0\"\n\nmodule Foo\n  def self.bar; end\nend\n\ndef baz; end\n");
+        assert_eq!(library.top_level_statements().iter().count(), 4);
+        assert!(!library.has_top_level_code());
+
+        let script = parse(b"require \"set\"\n\ndef main; end\n\nmain\n");
+        assert_eq!(script.top_level_statements().iter().count(), 3);
+        assert!(script.has_top_level_code());
+
+        assert!(!parse(b"").has_top_level_code());
     }
 
     #[test]
-    fn visitor_traversal_test() {
-        use crate::{Node, Visit};
+    fn interpolated_symbol_test() {
+        let result = parse(b":foo\n:\"foo#{bar}\"\n:\"foo#{}#{\"bar\"}\"\n\"foo#{bar}\"");
+        let root = result.node();
+        let statements = root.as_program_node().unwrap().statements().body().iter().collect::<Vec<_>>();
+
+        let symbol_like = statements.iter().map(super::Node::is_symbol_like).collect::<Vec<_>>();
+        assert_eq!(symbol_like, vec![true, true, true, false]);
+
+        let dynamic = statements[1].as_interpolated_symbol_node().unwrap();
+        assert_eq!(dynamic.parts().iter().count(), 2);
+        assert!(!dynamic.is_all_static());
+        assert!(statements[2].as_interpolated_symbol_node().unwrap().is_all_static());
+    }
 
-        #[derive(Default)]
-        struct NodeCounts {
-            pre_parent: usize,
-            post_parent: usize,
-            pre_leaf: usize,
-            post_leaf: usize,
-        }
+    #[test]
+    fn assign_ids_test() {
+        use super::NodeType;
 
-        #[derive(Default)]
-        struct CountingVisitor {
-            counts: NodeCounts,
-        }
+        let before = parse(b"foo\nbar(1)\n");
+        let after = parse(b"x = 1\nfoo\nbar(1)\nbaz\n");
 
-        impl<'pr> Visit<'pr> for CountingVisitor {
-            fn visit_branch_node_enter(&mut self, _node: Node<'_>) {
-                self.counts.pre_parent += 1;
-            }
+        let before_ids = before.assign_ids();
+        let after_ids = after.assign_ids();
 
-            fn visit_branch_node_leave(&mut self) {
-                self.counts.post_parent += 1;
-            }
+        assert_eq!(before_ids.len(), 6);
+        assert_eq!(before_ids[&(0, 3, NodeType::CallNode)], after_ids[&(6, 9, NodeType::CallNode)]);
+        assert_eq!(before_ids[&(4, 10, NodeType::CallNode)], after_ids[&(10, 16, NodeType::CallNode)]);
+        assert_eq!(before_ids[&(8, 9, NodeType::IntegerNode)], after_ids[&(14, 15, NodeType::IntegerNode)]);
+        assert_ne!(after_ids[&(4, 5, NodeType::IntegerNode)], after_ids[&(14, 15, NodeType::IntegerNode)]);
+        assert!(!before_ids.values().any(|&id| id == after_ids[&(17, 20, NodeType::CallNode)]));
+    }
 
-            fn visit_leaf_node_enter(&mut self, _node: Node<'_>) {
-                self.counts.pre_leaf += 1;
-            }
+    #[test]
+    fn bool_literal_test() {
+        let result = parse(b"true; false; nil; self; 1");
+        let root = result.node();
+        let statements = root.as_program_node().unwrap().statements().body().iter().collect::<Vec<_>>();
+
+        assert_eq!(statements.iter().map(super::Node::as_bool).collect::<Vec<_>>(), vec![Some(true), Some(false), None, None, None]);
+        assert_eq!(statements.iter().map(super::Node::is_nil).collect::<Vec<_>>(), vec![false, false, true, false, false]);
+        assert_eq!(statements.iter().map(super::Node::is_self).collect::<Vec<_>>(), vec![false, false, false, true, false]);
+    }
 
-            fn visit_leaf_node_leave(&mut self) {
-                self.counts.post_leaf += 1;
-            }
-        }
+    #[test]
+    fn method_definitions_test() {
+        let source = "def main; end\n\nmodule Foo\n  class Bar::Baz\n    def to_s; end\n    def self.build; end\n\n    class << self\n      def create; end\n    end\n  end\n\n  def helper; end\n  def obj.special; end\n\n  class << obj\n    def custom; end\n  end\nend\n";
+        let result = parse(source.as_ref());
 
-        let source = r#"
-module Example
-  x = call_func(3, 4)
-  y = x.call_func 5, 6
-end
-"#;
+        let definitions = result.method_definitions().iter().map(|definition| (definition.name.clone(), definition.owner.clone(), definition.singleton)).collect::<Vec<_>>();
+        assert_eq!(
+            definitions,
+            vec![
+                ("main".to_string(), String::new(), false),
+                ("to_s".to_string(), "Foo::Bar::Baz".to_string(), false),
+                ("build".to_string(), "Foo::Bar::Baz".to_string(), true),
+                ("create".to_string(), "Foo::Bar::Baz".to_string(), true),
+                ("helper".to_string(), "Foo".to_string(), false),
+                ("special".to_string(), "Foo".to_string(), false),
+                ("custom".to_string(), "Foo".to_string(), false),
+            ]
+        );
+
+        assert_eq!(result.method_definitions()[1].location.as_slice(), b"def to_s; end");
+    }
+
+    #[test]
+    fn sloc_test() {
+        let source = "def foo(a)\n  # Double it.\n\n  b = a * 2 # inline\n\n=begin\nignored\n=end\n  b\nend\n";
         let result = parse(source.as_ref());
-        let node = result.node();
-        let mut visitor = CountingVisitor::default();
-        visitor.visit(&node);
+        let comments = result.comments().collect::<Vec<_>>();
+        let def = result.top_level_statements().iter().next().unwrap();
 
-        assert_eq!(7, visitor.counts.pre_parent);
-        assert_eq!(7, visitor.counts.post_parent);
-        assert_eq!(6, visitor.counts.pre_leaf);
-        assert_eq!(6, visitor.counts.post_leaf);
+        assert_eq!(def.sloc(&comments), 4);
+        assert_eq!(def.sloc(&[]), 8);
+        assert_eq!(result.node().sloc(&comments), 4);
     }
 
     #[test]
-    fn visitor_lifetime_test() {
-        use crate::{Node, Visit};
+    fn structure_debug_strings_test() {
+        let a = parse(b"\"a\"");
+        let b = parse(b"\"b\"");
 
-        #[derive(Default)]
-        struct StackingNodeVisitor<'a> {
-            stack: Vec<Node<'a>>,
-            max_depth: usize,
-        }
+        assert_ne!(a.node().structure_debug(), b.node().structure_debug());
+        assert!(a.node().structure_debug().contains("unescaped: \"a\"\n"));
+    }
 
-        impl<'pr> Visit<'pr> for StackingNodeVisitor<'pr> {
-            fn visit_branch_node_enter(&mut self, node: Node<'pr>) {
-                self.stack.push(node);
-            }
+    #[test]
+    fn serializations_string_fields_test() {
+        use super::{Event, Scalar};
 
-            fn visit_branch_node_leave(&mut self) {
-                self.stack.pop();
-            }
+        let result = parse(b"'hi'");
+        let root = result.node();
+        let string = root.as_program_node().unwrap().statements().body().iter().next().unwrap();
 
-            fn visit_leaf_node_leave(&mut self) {
-                self.max_depth = self.max_depth.max(self.stack.len());
-            }
-        }
+        let fields = string.events().filter_map(|event| if let Event::Field(name) = event { Some(name) } else { None }).collect::<Vec<_>>();
+        assert_eq!(fields, vec!["flags", "opening_loc", "content_loc", "closing_loc", "unescaped"]);
+        assert!(string.events().any(|event| matches!(event, Event::Scalar(Scalar::Bytes(b"hi")))));
 
-        let source = r#"
-module Example
-  x = call_func(3, 4)
-  y = x.call_func 5, 6
-end
-"#;
-        let result = parse(source.as_ref());
-        let node = result.node();
-        let mut visitor = StackingNodeVisitor::default();
-        visitor.visit(&node);
+        assert!(string.structure_debug().contains("unescaped: \"hi\"\n"));
+        assert!(result.fingerprint().contains(" unescaped=\"hi\""));
 
-        assert_eq!(0, visitor.stack.len());
-        assert_eq!(5, visitor.max_depth);
+        #[cfg(feature = "json")]
+        assert_eq!(string.to_json_value()["unescaped"], "hi");
     }
 }
@@ -0,0 +1,277 @@
+//! Translation of Ruby regular expressions into the syntax accepted by the
+//! [`regex`] crate, so that simple Ruby regular expressions can be evaluated
+//! from Rust.
+//!
+//! The `regex` crate deliberately leaves out features that require
+//! backtracking, so the following Ruby constructs cannot be translated and
+//! cause the translation to return `None`:
+//!
+//! * backreferences (`\1`, `\k<name>`) and subexpression calls (`\g<name>`)
+//! * lookahead and lookbehind (`(?=`, `(?!`, `(?<=`, `(?<!`)
+//! * atomic groups (`(?>`), the absent operator (`(?~`), and conditionals (`(?(`)
+//! * possessive quantifiers (`*+`, `++`, `?+`)
+//! * the `\K`, `\G`, `\R`, `\X`, and `\Z` escapes, and control/meta escapes
+//!   (`\cx`, `\C-x`, `\M-x`)
+//! * patterns with the EUC-JP (`/e`) or Windows-31J (`/s`) encoding flags
+//!
+//! Everything else is rewritten to keep Ruby's semantics: `\d`, `\w`, `\s`, and
+//! `\h` stay ASCII-only, `^` and `$` stay line anchors, and the `m` option
+//! (in flags or inline groups) becomes the `s` option, since in Ruby it makes
+//! `.` match newlines. Unicode property names are passed through unchanged, so
+//! names that the `regex` crate does not know will fail when compiled.
+
+use crate::RegularExpressionNode;
+
+/// The options to build a translated pattern with, so that it matches the same
+/// way the original Ruby regular expression would.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RegexBuilderFlags {
+    /// Whether the pattern should ignore case (`/i`).
+    pub case_insensitive: bool,
+
+    /// Whether `.` should also match newlines (`/m` in Ruby).
+    pub dot_matches_new_line: bool,
+
+    /// Whether whitespace and `#` comments should be ignored (`/x`).
+    pub ignore_whitespace: bool,
+}
+
+impl RegexBuilderFlags {
+    /// Compiles the given translated pattern with these flags. Multi-line mode
+    /// is always enabled, because `^` and `$` always match at line boundaries
+    /// in Ruby.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the `regex` crate rejects the pattern.
+    pub fn build(&self, pattern: &str) -> Result<regex::Regex, regex::Error> {
+        regex::RegexBuilder::new(pattern)
+            .multi_line(true)
+            .case_insensitive(self.case_insensitive)
+            .dot_matches_new_line(self.dot_matches_new_line)
+            .ignore_whitespace(self.ignore_whitespace)
+            .build()
+    }
+}
+
+impl RegularExpressionNode<'_> {
+    /// Translates this regular expression into a pattern and a set of flags
+    /// that the `regex` crate can compile (see [`RegexBuilderFlags::build`]).
+    /// Returns `None` if the regular expression uses a Ruby-only feature that
+    /// has no equivalent; see the module documentation for the full list.
+    #[must_use]
+    pub fn to_regex_pattern(&self) -> Option<(String, RegexBuilderFlags)> {
+        if self.is_euc_jp() || self.is_windows_31j() {
+            return None;
+        }
+
+        let flags = RegexBuilderFlags {
+            case_insensitive: self.is_ignore_case(),
+            dot_matches_new_line: self.is_multi_line(),
+            ignore_whitespace: self.is_extended(),
+        };
+
+        let content = std::str::from_utf8(self.content_loc().as_slice()).ok()?;
+        translate(content, flags.ignore_whitespace).map(|pattern| (pattern, flags))
+    }
+}
+
+/// Pushes an ASCII-only character class for the given escape (`d`, `w`, `s`,
+/// `h`, or their negated uppercase forms). Inside of a character class the
+/// positive forms are pushed as bare ranges, and the negated forms as nested
+/// classes.
+fn push_ascii_class(out: &mut String, escape: char, in_class: bool) {
+    let ranges = match escape.to_ascii_lowercase() {
+        'd' => "0-9",
+        'w' => "0-9A-Za-z_",
+        's' => "\\t\\n\\x0B\\x0C\\r\\x20",
+        _ => "0-9A-Fa-f",
+    };
+
+    if escape.is_ascii_lowercase() {
+        if in_class {
+            out.push_str(ranges);
+        } else {
+            out.push('[');
+            out.push_str(ranges);
+            out.push(']');
+        }
+    } else {
+        out.push_str("[^");
+        out.push_str(ranges);
+        out.push(']');
+    }
+}
+
+/// Returns up to `max` characters matching `pred` starting at `index`.
+fn take_while(chars: &[char], index: usize, max: usize, pred: impl Fn(char) -> bool) -> &[char] {
+    let rest = &chars[index..];
+    let count = rest.iter().take(max).take_while(|char| pred(**char)).count();
+    &rest[..count]
+}
+
+/// Pushes a `\x{...}` escape for the given hexadecimal code point.
+fn push_code_point(out: &mut String, hex: &str) {
+    out.push_str("\\x{");
+    out.push_str(hex);
+    out.push('}');
+}
+
+/// Translates the escape sequence following a backslash, advancing `index` past
+/// it. Returns `None` if the escape has no equivalent in the `regex` crate.
+fn translate_escape(chars: &[char], index: &mut usize, out: &mut String, in_class: bool, extended: bool) -> Option<()> {
+    let escape = *chars.get(*index)?;
+    *index += 1;
+
+    match escape {
+        '1'..='9' | 'k' | 'g' | 'K' | 'G' | 'R' | 'X' | 'Z' | 'c' | 'C' | 'M' => return None,
+        'd' | 'D' | 'w' | 'W' | 's' | 'S' | 'h' | 'H' => push_ascii_class(out, escape, in_class),
+        'e' => out.push_str("\\x1B"),
+        'a' => out.push_str("\\x07"),
+        '/' => out.push('/'),
+        '0' => {
+            let digits = take_while(chars, *index, 2, |char| char.is_digit(8));
+            *index += digits.len();
+            let digits = digits.iter().collect::<String>();
+            let value = u32::from_str_radix(&digits, 8).unwrap_or(0);
+            push_code_point(out, &format!("{value:X}"));
+        },
+        'x' => {
+            let digits = take_while(chars, *index, 2, |char| char.is_ascii_hexdigit());
+            if digits.is_empty() {
+                return None;
+            }
+            *index += digits.len();
+            push_code_point(out, &digits.iter().collect::<String>());
+        },
+        'u' => {
+            if chars.get(*index) == Some(&'{') {
+                let digits = take_while(chars, *index + 1, usize::MAX, |char| char != '}');
+                *index += digits.len() + 2;
+                // A single `\u{...}` can hold several space separated code points.
+                for code_point in digits.iter().collect::<String>().split_whitespace() {
+                    push_code_point(out, code_point);
+                }
+            } else {
+                let digits = take_while(chars, *index, 4, |char| char.is_ascii_hexdigit());
+                if digits.len() != 4 {
+                    return None;
+                }
+                *index += 4;
+                push_code_point(out, &digits.iter().collect::<String>());
+            }
+        },
+        'p' | 'P' if chars.get(*index) == Some(&'{') && chars.get(*index + 1) == Some(&'^') => {
+            out.push_str(if escape == 'p' { "\\P{" } else { "\\p{" });
+            *index += 2;
+        },
+        'A' | 'z' | 'b' | 'B' | 'n' | 't' | 'r' | 'f' | 'v' | 'p' | 'P' => {
+            out.push('\\');
+            out.push(escape);
+        },
+        _ if escape.is_ascii_alphanumeric() => out.push(escape),
+        // An escaped whitespace character is a literal in Ruby's extended
+        // mode, but the regex crate would ignore it.
+        _ if extended && escape.is_whitespace() => push_code_point(out, &format!("{:X}", u32::from(escape))),
+        _ => {
+            // Any other escaped character is a literal, which the
+            // regex crate only allows to be escaped if it is
+            // punctuation.
+            if escape.is_ascii_punctuation() {
+                out.push('\\');
+            }
+            out.push(escape);
+        },
+    }
+
+    Some(())
+}
+
+/// Translates the source of a Ruby regular expression. Returns `None` if the
+/// source uses a construct that cannot be expressed with the `regex` crate.
+fn translate(pattern: &str, extended: bool) -> Option<String> {
+    let chars = pattern.chars().collect::<Vec<_>>();
+    let mut out = String::with_capacity(pattern.len());
+    let mut class_depth = 0_usize;
+    let mut index = 0;
+
+    while index < chars.len() {
+        let char = chars[index];
+        index += 1;
+
+        match char {
+            '\\' => translate_escape(&chars, &mut index, &mut out, class_depth > 0, extended)?,
+            '#' if extended && class_depth == 0 => {
+                // A comment, which runs to the end of the line. It is dropped
+                // so that any brackets or groups in it aren't translated.
+                index += take_while(&chars, index, usize::MAX, |char| char != '\n').len() + 1;
+            },
+            '[' if class_depth > 0 && chars.get(index) == Some(&':') => {
+                // A POSIX bracket expression like `[:alpha:]`, which both
+                // dialects support.
+                let name = take_while(&chars, index, usize::MAX, |char| char != ']');
+                index += name.len() + 1;
+                out.push('[');
+                out.extend(name);
+                out.push(']');
+            },
+            '[' => {
+                class_depth += 1;
+                out.push('[');
+            },
+            ']' if class_depth > 0 => {
+                class_depth -= 1;
+                out.push(']');
+            },
+            _ if class_depth > 0 => {
+                // Whitespace in a class is significant in Ruby's extended mode,
+                // but the regex crate ignores it.
+                if extended && char.is_whitespace() {
+                    push_code_point(&mut out, &format!("{:X}", u32::from(char)));
+                } else {
+                    out.push(char);
+                }
+            },
+            '(' if chars.get(index) == Some(&'?') => {
+                index += 1;
+
+                match chars.get(index)? {
+                    ':' => {
+                        index += 1;
+                        out.push_str("(?:");
+                    },
+                    '#' => {
+                        // A comment group, which is dropped entirely.
+                        let comment = take_while(&chars, index, usize::MAX, |char| char != ')');
+                        index += comment.len() + 1;
+                    },
+                    '<' | '\'' if chars.get(index + 1).is_some_and(|char| char.is_alphabetic() || *char == '_') => {
+                        let close = if chars[index] == '<' { '>' } else { '\'' };
+                        let name = take_while(&chars, index + 1, usize::MAX, |char| char != close);
+                        index += name.len() + 2;
+                        out.push_str("(?P<");
+                        out.extend(name);
+                        out.push('>');
+                    },
+                    'i' | 'm' | 'x' | '-' => {
+                        let options = take_while(&chars, index, usize::MAX, |char| matches!(char, 'i' | 'm' | 'x' | '-'));
+                        index += options.len();
+                        out.push_str("(?");
+                        out.extend(options.iter().map(|&option| if option == 'm' { 's' } else { option }));
+                    },
+                    _ => return None,
+                }
+            },
+            '*' | '+' | '?' | '}' => {
+                out.push(char);
+                if chars.get(index) == Some(&'+') {
+                    return None;
+                }
+            },
+            '{' if chars.get(index) == Some(&',') => out.push_str("{0"),
+            _ => out.push(char),
+        }
+    }
+
+    Some(out)
+}
@@ -0,0 +1,222 @@
+//! A symbol table of the definitions in a syntax tree.
+//!
+//! The table records the classes, modules, methods, constants, and local
+//! variables that are defined, for IDE-style tooling that needs to know what is
+//! defined where without walking the tree itself.
+
+use std::collections::HashSet;
+
+use crate::{walk_scopes, ConstantId, Location, Node, Scope, ScopeVisit};
+
+/// The kind of a [`Symbol`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolKind {
+    /// A class definition (`class Foo`).
+    Class,
+
+    /// A module definition (`module Foo`).
+    Module,
+
+    /// An instance method definition (`def foo`).
+    Method,
+
+    /// A singleton method definition, either `def self.foo` or a `def` inside
+    /// of `class << self`.
+    SingletonMethod,
+
+    /// A constant assignment (`FOO = 1` or `Foo::BAR = 1`).
+    Constant,
+
+    /// A local variable or parameter. Each local is recorded once per method,
+    /// class, or module, at its first assignment.
+    Local,
+}
+
+/// A single definition in a [`SymbolTable`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Symbol {
+    /// The name as written, e.g. `"Foo::Bar"` for `class Foo::Bar`.
+    pub name: String,
+
+    /// The kind of definition.
+    pub kind: SymbolKind,
+
+    /// The byte offset of the start of the definition.
+    pub start_offset: usize,
+
+    /// The byte offset of the end of the definition. For classes, modules, and
+    /// methods this covers the whole body.
+    pub end_offset: usize,
+
+    /// The index of the class, module, or method that the definition appears
+    /// in, or `None` at the top level.
+    pub parent: Option<usize>,
+}
+
+/// The definitions in a syntax tree along with how they are nested.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SymbolTable {
+    symbols: Vec<Symbol>,
+}
+
+impl SymbolTable {
+    /// Builds the symbol table of the given tree.
+    #[must_use]
+    pub fn build(root: &Node<'_>) -> Self {
+        let mut builder = Builder::default();
+        walk_scopes(root, &mut Scope::default(), &mut builder);
+        Self { symbols: builder.symbols }
+    }
+
+    /// Returns every symbol in source order. A symbol's `parent` is an index
+    /// into this slice.
+    #[must_use]
+    pub fn symbols(&self) -> &[Symbol] {
+        &self.symbols
+    }
+
+    /// Returns the symbols with the given name.
+    pub fn lookup<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Symbol> + 'a {
+        self.symbols.iter().filter(move |symbol| symbol.name == name)
+    }
+
+    /// Returns the parent of the given symbol.
+    #[must_use]
+    pub fn parent(&self, symbol: &Symbol) -> Option<&Symbol> {
+        symbol.parent.map(|index| &self.symbols[index])
+    }
+
+    /// Returns the symbols directly inside the symbol at the given index.
+    pub fn children(&self, index: usize) -> impl Iterator<Item = &Symbol> + '_ {
+        self.symbols.iter().filter(move |symbol| symbol.parent == Some(index))
+    }
+
+    /// Returns the innermost symbol whose definition covers the given byte
+    /// offset.
+    #[must_use]
+    pub fn symbol_at(&self, offset: usize) -> Option<&Symbol> {
+        self.symbols.iter().filter(|symbol| symbol.start_offset <= offset && offset < symbol.end_offset).min_by_key(|symbol| symbol.end_offset - symbol.start_offset)
+    }
+
+    /// Returns the innermost class, module, or method that the given byte
+    /// offset is inside of.
+    #[must_use]
+    pub fn scope_at(&self, offset: usize) -> Option<&Symbol> {
+        self.symbols
+            .iter()
+            .filter(|symbol| !matches!(symbol.kind, SymbolKind::Constant | SymbolKind::Local))
+            .filter(|symbol| symbol.start_offset <= offset && offset < symbol.end_offset)
+            .min_by_key(|symbol| symbol.end_offset - symbol.start_offset)
+    }
+
+    /// Returns the name of the given symbol qualified by the classes and
+    /// modules it is defined in, using `#` for instance methods and `.` for
+    /// singleton methods (e.g. `"Foo::Bar#baz"`). Locals are not visible
+    /// outside of their scope, so their plain name is returned.
+    #[must_use]
+    pub fn qualified_name(&self, symbol: &Symbol) -> String {
+        let Some(parent) = self.parent(symbol).filter(|_| symbol.kind != SymbolKind::Local) else { return symbol.name.clone() };
+
+        let separator = match symbol.kind {
+            SymbolKind::Method => "#",
+            SymbolKind::SingletonMethod => ".",
+            _ => "::",
+        };
+
+        format!("{}{separator}{}", self.qualified_name(parent), symbol.name)
+    }
+}
+
+/// The visitor that collects the symbols.
+#[derive(Default)]
+struct Builder {
+    symbols: Vec<Symbol>,
+
+    /// The indices of the enclosing classes, modules, and methods.
+    scopes: Vec<usize>,
+
+    /// The locals that were recorded already, by scope.
+    locals: HashSet<(Option<usize>, String)>,
+}
+
+impl Builder {
+    /// Records a symbol in the current scope and returns its index.
+    fn push(&mut self, name: String, kind: SymbolKind, location: &Location<'_>) -> usize {
+        self.symbols.push(Symbol {
+            name,
+            kind,
+            start_offset: location.start_offset(),
+            end_offset: location.end_offset(),
+            parent: self.scopes.last().copied(),
+        });
+        self.symbols.len() - 1
+    }
+
+    /// Records a symbol and makes it the current scope until the node is
+    /// left.
+    fn push_scope(&mut self, name: String, kind: SymbolKind, location: &Location<'_>) {
+        let index = self.push(name, kind, location);
+        self.scopes.push(index);
+    }
+
+    /// Records a local in the current scope, unless it was recorded already.
+    fn local(&mut self, name: &ConstantId<'_>, location: &Location<'_>) {
+        let name = String::from_utf8_lossy(name.as_slice()).into_owned();
+        if self.locals.insert((self.scopes.last().copied(), name.clone())) {
+            self.push(name, SymbolKind::Local, location);
+        }
+    }
+
+    /// Records an optional local (e.g. the name of `*rest`), if it is named.
+    fn optional_local(&mut self, name: Option<ConstantId<'_>>, location: &Location<'_>) {
+        if let Some(name) = name {
+            self.local(&name, location);
+        }
+    }
+}
+
+impl<'pr> ScopeVisit<'pr> for Builder {
+    fn visit(&mut self, node: &Node<'pr>, scope: &Scope) -> bool {
+        let location = node.location();
+        let text = |location: Location<'_>| String::from_utf8_lossy(location.as_slice()).into_owned();
+
+        match node {
+            Node::ClassNode { .. } => self.push_scope(node.as_class_node().map(|node| text(node.constant_path().location())).unwrap_or_default(), SymbolKind::Class, &location),
+            Node::ModuleNode { .. } => self.push_scope(node.as_module_node().map(|node| text(node.constant_path().location())).unwrap_or_default(), SymbolKind::Module, &location),
+            Node::DefNode { .. } => {
+                let Some(def) = node.as_def_node() else { return true };
+                let singleton = scope.singleton || def.receiver().is_some_and(|receiver| receiver.is_self());
+                let kind = if singleton { SymbolKind::SingletonMethod } else { SymbolKind::Method };
+                self.push_scope(String::from_utf8_lossy(def.name().as_slice()).into_owned(), kind, &location);
+            },
+            Node::ConstantWriteNode { .. } => {
+                if let Some(write) = node.as_constant_write_node() {
+                    self.push(String::from_utf8_lossy(write.name().as_slice()).into_owned(), SymbolKind::Constant, &location);
+                }
+            },
+            Node::ConstantPathWriteNode { .. } => {
+                if let Some(write) = node.as_constant_path_write_node() {
+                    self.push(text(write.target().location()), SymbolKind::Constant, &location);
+                }
+            },
+            Node::LocalVariableWriteNode { .. } => node.as_local_variable_write_node().iter().for_each(|node| self.local(&node.name(), &location)),
+            Node::LocalVariableTargetNode { .. } => node.as_local_variable_target_node().iter().for_each(|node| self.local(&node.name(), &location)),
+            Node::RequiredParameterNode { .. } => node.as_required_parameter_node().iter().for_each(|node| self.local(&node.name(), &location)),
+            Node::OptionalParameterNode { .. } => node.as_optional_parameter_node().iter().for_each(|node| self.local(&node.name(), &location)),
+            Node::RequiredKeywordParameterNode { .. } => node.as_required_keyword_parameter_node().iter().for_each(|node| self.local(&node.name(), &location)),
+            Node::OptionalKeywordParameterNode { .. } => node.as_optional_keyword_parameter_node().iter().for_each(|node| self.local(&node.name(), &location)),
+            Node::BlockLocalVariableNode { .. } => node.as_block_local_variable_node().iter().for_each(|node| self.local(&node.name(), &location)),
+            Node::RestParameterNode { .. } => node.as_rest_parameter_node().iter().for_each(|node| self.optional_local(node.name(), &location)),
+            Node::KeywordRestParameterNode { .. } => node.as_keyword_rest_parameter_node().iter().for_each(|node| self.optional_local(node.name(), &location)),
+            Node::BlockParameterNode { .. } => node.as_block_parameter_node().iter().for_each(|node| self.optional_local(node.name(), &location)),
+            _ => {},
+        }
+        true
+    }
+
+    fn leave(&mut self, node: &Node<'pr>) {
+        if matches!(node, Node::ClassNode { .. } | Node::ModuleNode { .. } | Node::DefNode { .. }) {
+            self.scopes.pop();
+        }
+    }
+}